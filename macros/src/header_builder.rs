@@ -1,12 +1,12 @@
 use crate::display_delegate::display;
-use proc_macro2::{Group, Span, TokenTree};
-use quote::ToTokens;
+use proc_macro2::{Group, Ident, Span, TokenTree};
+use quote::{quote, ToTokens};
 use std::fmt::{Error, Formatter};
 use syn::punctuated::Punctuated;
-use syn::token::{Colon2, Semi};
+use syn::token::Semi;
 use syn::{
-    self, Expr, ExprUnsafe, FnArg, GenericParam, Pat, PatIdent, PatType, PathSegment, Signature,
-    Stmt,
+    self, Expr, ExprUnsafe, FnArg, GenericParam, Pat, PatIdent, PatType, Path, PathSegment,
+    ReturnType, Signature, Stmt, Type, TypePath,
 };
 
 const MOCKTOPUS_CRATE_NAME: &str = "__mocktopus_crate__";
@@ -14,6 +14,14 @@ const STD_CRATE_NAME: &str = "__mocktopus_std__";
 const ARGS_TO_CONTINUE_NAME: &str = "__mocktopus_args_to_continue__";
 const ARGS_TO_RETURN_NAME: &str = "__mocktopus_args_to_return__";
 const UNWIND_DATA_NAME: &str = "__mocktopus_unwind_data__";
+const PANIC_MESSAGE_NAME: &str = "__mocktopus_panic_message__";
+const TIMING_GUARD_NAME: &str = "__mocktopus_timing_guard__";
+const RECEIVER_NAME: &str = "__mocktopus_receiver__";
+const POSITIONAL_ARG_PREFIX: &str = "__mocktopus_arg_";
+const PRIMITIVE_TYPES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32",
+    "u64", "u128", "usize",
+];
 
 macro_rules! error_msg {
     ($msg:expr) => {
@@ -23,29 +31,39 @@ macro_rules! error_msg {
 
 pub enum FnHeaderBuilder<'a> {
     StaticFn,
-    StructImpl,
-    TraitDefault,
-    TraitImpl(&'a Punctuated<PathSegment, Colon2>),
+    StructImpl(&'a Type),
+    TraitDefault(&'a Ident),
+    TraitImpl(&'a Type, &'a Path),
 }
 
 impl<'a> FnHeaderBuilder<'a> {
-    pub fn build(&self, fn_decl: &Signature, fn_block_span: Span) -> Stmt {
+    /// Fails with an error pointing at the part of user's fn, which can't be handled
+    pub fn build(&self, fn_decl: &Signature, fn_block_span: Span) -> syn::Result<Stmt> {
         let fn_args = &fn_decl.inputs;
+        debug_assert!(
+            are_fn_args_idents(fn_args),
+            error_msg!("args destructured by patterns must be rebound before building header")
+        );
         let header_str = format!(
             r#"
             unsafe {{
                 extern crate mocktopus as {mocktopus};
                 extern crate std as {std_crate};
 
-                #[allow(clippy::forget_copy, clippy::forget_ref, clippy::forget_non_drop)]
+                #[allow(forgetting_copy_types, forgetting_references, clippy::forget_non_drop, clippy::swap_ptr_to_ref, clippy::let_unit_value)]
                 match {std_crate}::panic::catch_unwind({std_crate}::panic::AssertUnwindSafe (
-                        || {mocktopus}::mocking::Mockable::call_mock(&{full_fn_name}, {extract_args}))) {{
-                    Ok({mocktopus}::mocking::MockResult::Continue(mut {args_to_continue})) => {restore_args},
+                        || {call_mock})) {{
+                    Ok({mocktopus}::mocking::MockResult::Continue(mut {args_to_continue})) => {{
+                        {restore_args}
+                        {mocktopus}::mocking::start_timing({display_fn_name})
+                    }},
                     Ok({mocktopus}::mocking::MockResult::Return({args_to_return})) => {{
                         {forget_args}
-                        let returned = {std_crate}::mem::transmute_copy(&{args_to_return});
-                        {std_crate}::mem::forget({args_to_return});
-                        return returned;
+                        {return_mocked}
+                    }},
+                    Ok({mocktopus}::mocking::MockResult::Panic({panic_message})) => {{
+                        {forget_args}
+                        {std_crate}::panic::panic_any({panic_message});
                     }},
                     Err({unwind}) => {{
                         {forget_args}
@@ -55,20 +73,41 @@ impl<'a> FnHeaderBuilder<'a> {
             }}"#,
             mocktopus = MOCKTOPUS_CRATE_NAME,
             std_crate = STD_CRATE_NAME,
-            full_fn_name = display(|f| write_full_fn_name(f, self, fn_decl)),
-            extract_args = display(|f| write_extract_args(f, fn_args)),
+            call_mock = display(|f| write_call_mock(f, self, fn_decl)),
+            display_fn_name = display(|f| write_display_fn_name(f, self, fn_decl)),
             args_to_continue = ARGS_TO_CONTINUE_NAME,
             args_to_return = ARGS_TO_RETURN_NAME,
             restore_args = display(|f| write_restore_args(f, fn_args)),
             forget_args = display(|f| write_forget_args(f, fn_args)),
-            unwind = UNWIND_DATA_NAME
+            return_mocked = display(|f| write_return_mocked(f, &fn_decl.output)),
+            unwind = UNWIND_DATA_NAME,
+            panic_message = PANIC_MESSAGE_NAME
         );
-        let header_block = syn::parse_str::<ExprUnsafe>(&header_str)
-            .expect(error_msg!("generated header unparsable"));
-        create_call_site_spanned_stmt(header_block, fn_block_span)
+        let header_block = syn::parse_str::<ExprUnsafe>(&header_str).map_err(|error| {
+            syn::Error::new_spanned(
+                fn_decl,
+                format!("Mocktopus failed to make function mockable: {}", error),
+            )
+        })?;
+        let test_only_attr = if cfg!(feature = "test-only") {
+            quote!(#[cfg(test)])
+        } else {
+            quote!()
+        };
+        // Guard lives until the fn body finishes, so it measures the whole real call
+        let timing_guard = Ident::new(TIMING_GUARD_NAME, Span::call_site());
+        let header_stmt = quote!(#test_only_attr let #timing_guard = #header_block);
+        Ok(create_call_site_spanned_stmt(header_stmt, fn_block_span))
     }
 }
 
+fn are_fn_args_idents<T>(fn_args: &Punctuated<FnArg, T>) -> bool {
+    fn_args.iter().all(|fn_arg| match fn_arg {
+        FnArg::Typed(PatType { pat, .. }) => matches!(**pat, Pat::Ident(_)),
+        FnArg::Receiver(_) => true,
+    })
+}
+
 fn create_call_site_spanned_stmt(block: impl ToTokens, span: Span) -> Stmt {
     let token_stream = block
         .into_token_stream()
@@ -91,6 +130,125 @@ fn make_token_tree_span_call_site(mut token_tree: TokenTree, span: Span) -> Toke
     token_tree
 }
 
+fn write_call_mock(
+    f: &mut Formatter,
+    builder: &FnHeaderBuilder,
+    fn_decl: &Signature,
+) -> Result<(), Error> {
+    let full_fn_name = display(|f| write_full_fn_name(f, builder, fn_decl));
+    let display_fn_name = display(|f| write_display_fn_name(f, builder, fn_decl));
+    let extract_args = display(|f| write_extract_args(f, &fn_decl.inputs));
+    match *builder {
+        FnHeaderBuilder::TraitImpl(_, path) if has_receiver(&fn_decl.inputs) => write!(
+            f,
+            "{}::mocking::call_trait_mock(&{}, {}, {}, {}, {}, {})",
+            MOCKTOPUS_CRATE_NAME,
+            full_fn_name,
+            display_fn_name,
+            display(|f| write_trait_method_name(f, path, fn_decl)),
+            extract_args,
+            display(|f| write_split_receiver(f, &fn_decl.inputs)),
+            display(|f| write_join_receiver(f, &fn_decl.inputs)),
+        ),
+        _ => write!(
+            f,
+            "{}::mocking::Mockable::call_mock_named(&{}, {}, {})",
+            MOCKTOPUS_CRATE_NAME, full_fn_name, display_fn_name, extract_args
+        ),
+    }
+}
+
+fn trait_ident<T>(path: &Punctuated<PathSegment, T>) -> &Ident {
+    &path.last().expect(error_msg!("trait path empty")).ident
+}
+
+/// Writes expression evaluating to human readable fully qualified fn name, e.g. `crate::module::Struct::method`
+fn write_display_fn_name(
+    f: &mut Formatter,
+    builder: &FnHeaderBuilder,
+    fn_decl: &Signature,
+) -> Result<(), Error> {
+    write!(
+        f,
+        "{std}::concat!({std}::module_path!(), \"::",
+        std = STD_CRATE_NAME
+    )?;
+    match *builder {
+        FnHeaderBuilder::StaticFn => (),
+        FnHeaderBuilder::StructImpl(self_ty) => {
+            write!(f, "{}::", display(|f| write_type_name(f, self_ty)))?
+        }
+        FnHeaderBuilder::TraitDefault(trait_ident) => write!(f, "{}::", trait_ident)?,
+        FnHeaderBuilder::TraitImpl(self_ty, path) => write!(
+            f,
+            "<{} as {}>::",
+            display(|f| write_type_name(f, self_ty)),
+            trait_ident(&path.segments)
+        )?,
+    }
+    write!(f, "{}\")", fn_decl.ident)
+}
+
+/// Writes expression evaluating to trait method name used for keying mocks of all implementors,
+/// e.g. `crate::module::Trait::method` or `std::fmt::Display::fmt` for trait paths with leading `::`
+fn write_trait_method_name(
+    f: &mut Formatter,
+    path: &Path,
+    fn_decl: &Signature,
+) -> Result<(), Error> {
+    write!(f, "{}::concat!(", STD_CRATE_NAME)?;
+    if path.leading_colon.is_none() {
+        write!(f, "{}::module_path!(), \"::\", ", STD_CRATE_NAME)?;
+    }
+    write!(f, "\"")?;
+    path.segments
+        .iter()
+        .try_for_each(|segment| write!(f, "{}::", segment.ident))?;
+    write!(f, "{}\")", fn_decl.ident)
+}
+
+fn write_type_name(f: &mut Formatter, ty: &Type) -> Result<(), Error> {
+    match *ty {
+        Type::Path(TypePath {
+            qself: None,
+            ref path,
+        }) => {
+            let mut segments = path.segments.iter();
+            if let Some(first) = segments.next() {
+                write!(f, "{}", first.ident)?;
+            }
+            segments.try_for_each(|segment| write!(f, "::{}", segment.ident))
+        }
+        _ => write!(f, "{}", ty.to_token_stream().to_string().escape_default()),
+    }
+}
+
+fn has_receiver<T>(fn_args: &Punctuated<FnArg, T>) -> bool {
+    iter_fn_arg_names(fn_args)
+        .next()
+        .is_some_and(|name| name == "self")
+}
+
+fn write_split_receiver<T>(f: &mut Formatter, fn_args: &Punctuated<FnArg, T>) -> Result<(), Error> {
+    write!(f, "|({}, ", RECEIVER_NAME)?;
+    write_positional_args(f, fn_args.len() - 1)?;
+    write!(f, ")| ({}, (", RECEIVER_NAME)?;
+    write_positional_args(f, fn_args.len() - 1)?;
+    write!(f, "))")
+}
+
+fn write_join_receiver<T>(f: &mut Formatter, fn_args: &Punctuated<FnArg, T>) -> Result<(), Error> {
+    write!(f, "|{}, (", RECEIVER_NAME)?;
+    write_positional_args(f, fn_args.len() - 1)?;
+    write!(f, ")| ({}, ", RECEIVER_NAME)?;
+    write_positional_args(f, fn_args.len() - 1)?;
+    write!(f, ")")
+}
+
+fn write_positional_args(f: &mut Formatter, count: usize) -> Result<(), Error> {
+    (0..count).try_for_each(|index| write!(f, "{}{}, ", POSITIONAL_ARG_PREFIX, index))
+}
+
 fn write_full_fn_name(
     f: &mut Formatter,
     builder: &FnHeaderBuilder,
@@ -98,8 +256,8 @@ fn write_full_fn_name(
 ) -> Result<(), Error> {
     match *builder {
         FnHeaderBuilder::StaticFn => (),
-        FnHeaderBuilder::StructImpl | FnHeaderBuilder::TraitDefault => write!(f, "Self::")?,
-        FnHeaderBuilder::TraitImpl(path) => {
+        FnHeaderBuilder::StructImpl(_) | FnHeaderBuilder::TraitDefault(_) => write!(f, "Self::")?,
+        FnHeaderBuilder::TraitImpl(_, path) => {
             write!(f, "<Self as {}>::", display(|f| write_trait_path(f, path)))?
         }
     }
@@ -111,10 +269,8 @@ fn write_full_fn_name(
     )
 }
 
-fn write_trait_path<T: ToTokens + Clone>(
-    f: &mut Formatter,
-    path: &Punctuated<PathSegment, T>,
-) -> Result<(), Error> {
+/// Writes trait path with its leading `::` and generic args, e.g. `::std::convert::From<u32>`
+fn write_trait_path(f: &mut Formatter, path: &Path) -> Result<(), Error> {
     write!(f, "{}", path.into_token_stream())
 }
 
@@ -123,37 +279,130 @@ fn write_fn_generics(f: &mut Formatter, fn_decl: &Signature) -> Result<(), Error
         .generics
         .params
         .iter()
-        .filter_map(get_generic_param_name).try_for_each(|param| write!(f, "{},", param))
+        .filter_map(get_generic_param_name)
+        .try_for_each(|param| write!(f, "{},", param))
 }
 
 fn get_generic_param_name(param: &GenericParam) -> Option<String> {
     match *param {
         GenericParam::Type(ref type_param) => Some(type_param.ident.to_string()),
+        GenericParam::Const(ref const_param) => Some(const_param.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn write_return_mocked(f: &mut Formatter, output: &ReturnType) -> Result<(), Error> {
+    match *output {
+        // Value of never type can't exist, so the branch is unreachable
+        ReturnType::Type(_, ref ty) if matches!(**ty, Type::Never(_)) => {
+            write!(f, "match {} {{}}", ARGS_TO_RETURN_NAME)
+        }
+        ReturnType::Default => write!(f, "return {};", ARGS_TO_RETURN_NAME),
+        // Primitive types have no lifetimes, which could differ, so they don't need transmuting
+        ReturnType::Type(_, ref ty) if is_primitive_type(ty) => {
+            write!(f, "return {};", ARGS_TO_RETURN_NAME)
+        }
+        ReturnType::Type(_, ref ty) => match zero_sized_value(ty) {
+            // Zero sized values carry no data, so a fresh one is created instead of transmuting
+            Some(value) => write!(
+                f,
+                "{std}::mem::forget({ret});
+                return {value};",
+                std = STD_CRATE_NAME,
+                ret = ARGS_TO_RETURN_NAME,
+                value = value
+            ),
+            None => write!(
+                f,
+                "let returned = {std}::mem::transmute_copy(&{ret});
+                {std}::mem::forget({ret});
+                return returned;",
+                std = STD_CRATE_NAME,
+                ret = ARGS_TO_RETURN_NAME
+            ),
+        },
+    }
+}
+
+fn is_primitive_type(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(TypePath {
+            qself: None,
+            ref path,
+        }) => PRIMITIVE_TYPES
+            .iter()
+            .any(|primitive| path.is_ident(primitive)),
+        Type::Tuple(ref tuple) => tuple.elems.iter().all(is_primitive_type),
+        Type::Paren(ref paren) => is_primitive_type(&paren.elem),
+        _ => false,
+    }
+}
+
+/// Expression creating value of given type if it's known to be zero sized, e.g. `PhantomData`
+fn zero_sized_value(ty: &Type) -> Option<String> {
+    match *ty {
+        Type::Path(TypePath {
+            qself: None,
+            ref path,
+        }) if is_phantom_data_path(path) => Some("::core::marker::PhantomData".to_string()),
+        Type::Tuple(ref tuple) if tuple.elems.is_empty() => Some("()".to_string()),
+        Type::Paren(ref paren) => zero_sized_value(&paren.elem),
         _ => None,
     }
 }
 
+/// Matches bare `PhantomData` or its full path in `std` or `core`, other types may share its name
+fn is_phantom_data_path(path: &Path) -> bool {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    match segments.as_slice() {
+        [name] => path.leading_colon.is_none() && name == "PhantomData",
+        [krate, module, name] => {
+            (krate == "std" || krate == "core") && module == "marker" && name == "PhantomData"
+        }
+        _ => false,
+    }
+}
+
+fn zero_sized_arg_value(fn_arg: &FnArg) -> Option<String> {
+    match *fn_arg {
+        FnArg::Typed(PatType { ref ty, .. }) => zero_sized_value(ty),
+        FnArg::Receiver(_) => None,
+    }
+}
+
 fn write_extract_args<T>(f: &mut Formatter, fn_args: &Punctuated<FnArg, T>) -> Result<(), Error> {
     if fn_args.is_empty() {
         return write!(f, "()");
     }
     write!(f, "(")?;
-    for fn_arg_name in iter_fn_arg_names(fn_args) {
-        write!(
-            f,
-            "{}::mem::transmute_copy(&{}), ",
-            STD_CRATE_NAME, fn_arg_name
-        )?;
+    for (fn_arg, fn_arg_name) in fn_args.iter().zip(iter_fn_arg_names(fn_args)) {
+        match zero_sized_arg_value(fn_arg) {
+            Some(value) => write!(f, "{}, ", value)?,
+            None => write!(
+                f,
+                "{}::mem::transmute_copy(&{}), ",
+                STD_CRATE_NAME, fn_arg_name
+            )?,
+        }
     }
     write!(f, ")")
 }
 
+/// Swaps args with the ones returned by mock. For `&mut` args only the references are swapped,
+/// so values modified by mock through them stay modified when fn body runs.
 fn write_restore_args<T>(f: &mut Formatter, fn_args: &Punctuated<FnArg, T>) -> Result<(), Error> {
     if fn_args.is_empty() {
-        return writeln!(f, "()");
+        return Ok(());
     }
     writeln!(f, "{{")?;
-    for (fn_arg_index, fn_arg_name) in iter_fn_arg_names(fn_args).enumerate() {
+    let fn_arg_names = fn_args.iter().zip(iter_fn_arg_names(fn_args)).enumerate();
+    for (fn_arg_index, (_, fn_arg_name)) in
+        fn_arg_names.filter(|(_, (fn_arg, _))| zero_sized_arg_value(fn_arg).is_none())
+    {
         writeln!(
             f,
             "{}::mem::swap(&mut *(&{} as *const _ as *mut _), &mut {}.{});",
@@ -169,15 +418,16 @@ fn write_restore_args<T>(f: &mut Formatter, fn_args: &Punctuated<FnArg, T>) -> R
 }
 
 fn write_forget_args<T>(f: &mut Formatter, fn_args: &Punctuated<FnArg, T>) -> Result<(), Error> {
-    for fn_arg_name in iter_fn_arg_names(fn_args) {
+    let fn_arg_names = fn_args.iter().zip(iter_fn_arg_names(fn_args));
+    for (_, fn_arg_name) in
+        fn_arg_names.filter(|(fn_arg, _)| zero_sized_arg_value(fn_arg).is_none())
+    {
         writeln!(f, "{}::mem::forget({});", STD_CRATE_NAME, fn_arg_name)?;
     }
     Ok(())
 }
 
-fn iter_fn_arg_names<T>(
-    input_args: &'_ Punctuated<FnArg, T>,
-) -> impl Iterator<Item = String> + '_ {
+fn iter_fn_arg_names<T>(input_args: &'_ Punctuated<FnArg, T>) -> impl Iterator<Item = String> + '_ {
     input_args.iter().map(|fn_arg| {
         match fn_arg {
             FnArg::Receiver(_) => return "self".to_string(),
@@ -2,7 +2,9 @@ use crate::header_builder::FnHeaderBuilder;
 use proc_macro2::{TokenStream, TokenTree};
 use quote::{format_ident, quote, quote_spanned, ToTokens};
 use std::iter::FromIterator;
+use std::mem;
 use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::token::Comma;
 use syn::{
     parse_quote, Attribute, Block, FnArg, GenericParam, Generics, Ident, ImplItem, ImplItemMethod,
@@ -30,13 +32,35 @@ pub fn inject_item(item: &mut Item) {
     }
 }
 
+/// Describes item, which can't be annotated with `#[mockable]`
+pub fn unsupported_item_kind(item: &Item) -> Option<&'static str> {
+    let kind = match item {
+        Item::Fn(_) | Item::Mod(_) | Item::Trait(_) | Item::Impl(_) => return None,
+        // Items generated by macros are left untouched
+        Item::Macro(_) | Item::Verbatim(_) => return None,
+        Item::Const(_) => "a const",
+        Item::Enum(_) => "an enum",
+        Item::ExternCrate(_) => "an extern crate",
+        Item::ForeignMod(_) => "an extern block",
+        Item::Macro2(_) => "a macro",
+        Item::Static(_) => "a static",
+        Item::Struct(_) => "a struct",
+        Item::TraitAlias(_) => "a trait alias",
+        Item::Type(_) => "a type alias",
+        Item::Union(_) => "a union",
+        Item::Use(_) => "a use declaration",
+        _ => "this item",
+    };
+    Some(kind)
+}
+
 fn inject_fn(item_fn: &mut ItemFn) {
     inject_any_fn(
         Context::Fn,
         &FnHeaderBuilder::StaticFn,
         &item_fn.attrs,
         &mut item_fn.sig,
-        &mut *item_fn.block,
+        &mut item_fn.block,
     );
 }
 
@@ -65,7 +89,13 @@ fn inject_trait(item_trait: &mut ItemTrait) {
             ..
         }) = *item
         {
-            inject_any_fn(context, &FnHeaderBuilder::TraitDefault, attrs, sig, block);
+            inject_any_fn(
+                context,
+                &FnHeaderBuilder::TraitDefault(&item_trait.ident),
+                attrs,
+                sig,
+                block,
+            );
         }
     }
 }
@@ -75,8 +105,8 @@ fn inject_impl(item_impl: &mut ItemImpl) {
         return;
     }
     let builder = match item_impl.trait_ {
-        Some((_, ref path, _)) => FnHeaderBuilder::TraitImpl(&path.segments),
-        None => FnHeaderBuilder::StructImpl,
+        Some((_, ref path, _)) => FnHeaderBuilder::TraitImpl(&item_impl.self_ty, path),
+        None => FnHeaderBuilder::StructImpl(&item_impl.self_ty),
     };
 
     let context = Context::Impl {
@@ -100,9 +130,12 @@ fn inject_impl(item_impl: &mut ItemImpl) {
 }
 
 fn is_impl_fn_mockabile(builder: &FnHeaderBuilder, item_method: &ImplItemMethod) -> bool {
-    if let FnHeaderBuilder::TraitImpl(segments) = *builder {
-        if let Some(segment) = segments.last() {
-            if segment.arguments.is_empty() && segment.ident == "Drop" && item_method.sig.ident == "drop" {
+    if let FnHeaderBuilder::TraitImpl(_, path) = *builder {
+        if let Some(segment) = path.segments.last() {
+            if segment.arguments.is_empty()
+                && segment.ident == "Drop"
+                && item_method.sig.ident == "drop"
+            {
                 return false;
             }
         }
@@ -117,21 +150,27 @@ fn inject_any_fn(
     fn_decl: &mut Signature,
     block: &mut Block,
 ) {
+    // Header is not const-evaluable, so injecting it into a const fn would break its const callers
     if fn_decl.constness.is_some()
         || fn_decl.unsafety.is_some()
         || fn_decl.variadic.is_some()
+        || has_non_rust_abi(fn_decl)
         || is_not_mockable(attrs)
     {
         return;
     }
 
+    rebind_pattern_args(&mut fn_decl.inputs, block);
+
     if fn_decl.asyncness.is_some() {
         inject_async_fn(context, attrs, fn_decl, block);
     }
 
     unignore_fn_args(&mut fn_decl.inputs);
-    let header_stmt = builder.build(fn_decl, block.brace_token.span);
-    block.stmts.insert(0, header_stmt);
+    match builder.build(fn_decl, block.brace_token.span) {
+        Ok(header_stmt) => block.stmts.insert(0, header_stmt),
+        Err(error) => error.span().unwrap().error(error.to_string()).emit(),
+    }
 }
 
 // Transform async functions as `async-trait`
@@ -254,8 +293,7 @@ fn inject_async_fn(
     let outer_sig_inputs = outer_sig.inputs.iter_mut();
     for input in outer_sig_inputs {
         match input {
-            arg
-            @ FnArg::Receiver(Receiver {
+            arg @ FnArg::Receiver(Receiver {
                 reference: Some(_), ..
             }) => {
                 let (self_token, mutability) = match arg {
@@ -356,15 +394,49 @@ fn unignore_fn_args(inputs: &mut Punctuated<FnArg, Comma>) {
                 }
                 _ => continue,
             };
-            *pat = Box::new(Pat::Ident(PatIdent {
+            **pat = Pat::Ident(PatIdent {
                 by_ref: None,
                 mutability: None,
                 ident: Ident::new(&format!("__mocktopus_unignored_argument_{}__", i), span),
                 subpat: None,
                 attrs,
-            }));
+            });
+        }
+    }
+}
+
+fn has_non_rust_abi(fn_decl: &Signature) -> bool {
+    match fn_decl.abi {
+        Some(ref abi) => abi.name.as_ref().is_none_or(|name| name.value() != "Rust"),
+        None => false,
+    }
+}
+
+/// Replaces args destructured by patterns with plain named args and destructures them at the beginning of the body
+fn rebind_pattern_args(inputs: &mut Punctuated<FnArg, Comma>, block: &mut Block) {
+    let mut rebinding_stmts = Vec::new();
+    for (i, fn_arg) in inputs.iter_mut().enumerate() {
+        if let FnArg::Typed(PatType { ref mut pat, .. }) = *fn_arg {
+            if !is_pattern(pat) {
+                continue;
+            }
+            let ident = Ident::new(
+                &format!("__mocktopus_destructured_argument_{}__", i),
+                pat.span(),
+            );
+            let pattern = mem::replace(&mut **pat, parse_quote!(#ident));
+            rebinding_stmts.push(parse_quote!(let #pattern = #ident;));
         }
     }
+    block.stmts.splice(0..0, rebinding_stmts);
+}
+
+fn is_pattern(pat: &Pat) -> bool {
+    match *pat {
+        Pat::Ident(ref pat_ident) => pat_ident.by_ref.is_some() || pat_ident.subpat.is_some(),
+        Pat::Wild(_) => false,
+        _ => true,
+    }
 }
 
 const INJECTOR_STOPPER_ATTRS: [&str; 2] = ["mockable", "not_mockable"];
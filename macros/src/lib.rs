@@ -13,6 +13,7 @@ mod item_injector;
 
 use proc_macro::{Span, TokenStream};
 use quote::ToTokens;
+use syn::spanned::Spanned;
 
 /// Procedural macro, makes items and their sub-items mockable
 ///
@@ -33,6 +34,14 @@ use quote::ToTokens;
 /// #[mockable]
 /// fn mockable() { ... }
 /// ```
+/// Attributes of annotated functions are kept, so e.g. `#[track_caller]` functions still report
+/// location of their caller when they run normally, `#[must_use]` functions still trigger the lint when their
+/// results are ignored and `#[inline]` or `#[cold]` hints still apply.
+/// With `test-only` feature enabled the injected code is compiled only with `cfg(test)`,
+/// in other builds functions stay untouched. Without `enabled` feature, which is on by default,
+/// annotated items are always left untouched.
+/// Arguments destructured by patterns, e.g. `(a, b): (u32, u32)`, are passed to mocks whole
+/// and destructured only when the function runs normally.
 /// - struct impl blocks (makes all functions inside mockable)
 ///
 /// ```
@@ -49,6 +58,17 @@ use quote::ToTokens;
 ///     fn mockable() { ... }
 /// }
 /// ```
+/// Trait impl blocks using `#[async_trait]` must be annotated below it, so they are made mockable
+/// after their async functions are expanded into functions returning boxed futures.
+/// Their mocks return boxed futures too, e.g. created with `ready_future`.
+///
+/// ```
+/// #[async_trait]
+/// #[mockable]
+/// impl Trait for Structure {
+///     async fn mockable(&self) { ... }
+/// }
+/// ```
 /// - traits (makes all default functions inside mockable)
 ///
 /// ```
@@ -91,12 +111,23 @@ use quote::ToTokens;
 /// #[mockable]
 /// mod module;
 /// ```
-/// - const functions (they are impossible to mock)
+/// - const functions (they are impossible to mock, but stay usable in const contexts)
 /// - unsafe functions (they are impossible to mock)
+/// - functions with ABI other than `extern "Rust"`, e.g. `extern "C"` (they are impossible to mock)
 /// - any macro generated items (they are impossible to mock)
-/// - any other items
+///
+/// # Invalid to annotate
+/// Any other items, e.g. structs, statics or type aliases, cause a compilation error pointing at them:
+///
+/// ```
+/// #[mockable] // error: #[mockable] can't be used on a struct
+/// struct Struct;
+/// ```
 #[proc_macro_attribute]
 pub fn mockable(_: TokenStream, token_stream: TokenStream) -> TokenStream {
+    if !cfg!(feature = "enabled") {
+        return token_stream;
+    }
     let mut item: syn::Item = match syn::parse(token_stream.clone()) {
         Ok(item) => item,
         Err(err) => {
@@ -107,10 +138,82 @@ pub fn mockable(_: TokenStream, token_stream: TokenStream) -> TokenStream {
             return token_stream;
         }
     };
+    if let Some(kind) = item_injector::unsupported_item_kind(&item) {
+        item.span()
+            .unwrap()
+            .error(format!("#[mockable] can't be used on {}", kind))
+            .help("only functions, impl blocks, traits and modules can be made mockable")
+            .emit();
+        return token_stream;
+    }
     item_injector::inject_item(&mut item);
     item.into_token_stream().into()
 }
 
+/// Procedural macro, makes all items of the crate and their sub-items mockable
+///
+/// It must be used as an inner attribute of the crate root, which requires `custom_inner_attributes` feature.
+/// Because the attribute receives the standard prelude import injected by the compiler,
+/// `prelude_import` feature must be enabled as well:
+///
+/// ```
+/// #![feature(custom_inner_attributes, prelude_import, proc_macro_hygiene)]
+/// #![allow(internal_features)]
+/// #![cfg_attr(test, mocktopus::macros::mockable_crate)]
+///
+/// fn mockable() { ... }
+///
+/// mod module {
+///     fn mockable() { ... }
+/// }
+/// ```
+/// Items are made mockable exactly like if they were annotated with [mockable](attr.mockable.html),
+/// so items annotated with [not_mockable](attr.not_mockable.html) are left untouched.
+///
+/// Modules in separate files are not visible to the attribute, they must be made mockable in their
+/// own files with [mockable](attr.mockable.html) used as an inner attribute:
+///
+/// ```
+/// #![cfg_attr(test, mocktopus::macros::mockable)]
+///
+/// fn mockable() { ... }
+/// ```
+#[proc_macro_attribute]
+pub fn mockable_crate(_: TokenStream, token_stream: TokenStream) -> TokenStream {
+    if !cfg!(feature = "enabled") {
+        return token_stream;
+    }
+    let mut file: syn::File = match syn::parse(token_stream.clone()) {
+        Ok(file) => file,
+        Err(err) => {
+            Span::call_site()
+                .warning("Failed to make code mockable")
+                .error(format!("Failed to parse: {}", err))
+                .emit();
+            return token_stream;
+        }
+    };
+    for item in &mut file.items {
+        match item {
+            // Compiler doesn't recognize the prelude import as injected anymore and reports it as unused
+            syn::Item::Use(item_use) if is_prelude_import(item_use) => {
+                item_use
+                    .attrs
+                    .push(syn::parse_quote!(#[allow(unused_imports)]));
+            }
+            _ => item_injector::inject_item(item),
+        }
+    }
+    file.into_token_stream().into()
+}
+
+fn is_prelude_import(item_use: &syn::ItemUse) -> bool {
+    item_use
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("prelude_import"))
+}
+
 /// Procedural macro, guards items from being made mockable by enclosing item.
 ///
 /// # Valid to annotate
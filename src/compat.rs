@@ -0,0 +1,3 @@
+//! Adapters easing migration from other mocking libraries
+
+pub mod mockall;
@@ -0,0 +1,198 @@
+//! Expectations in the style of [mockall](https://docs.rs/mockall)
+//!
+//! It covers the most common pattern of mockall expectations for free functions.
+//! An expectation is created with [expect](fn.expect.html) instead of `mock.expect_function()`:
+//!
+//! ```
+//! #[mockable]
+//! fn get_string(id: u32) -> String {
+//!     "not mocked".to_string()
+//! }
+//!
+//! #[test]
+//! fn get_string_test() {
+//!     let _expectation = expect(get_string)
+//!         .with((args::eq(1),))
+//!         .times(1)
+//!         .returning(|id| format!("mocked {}", id));
+//!
+//!     assert_eq!("mocked 1", get_string(1));
+//! }
+//! ```
+//!
+//! The expectation is a regular mock set up in the current thread, it stays active as long as its
+//! [ExpectationGuard](struct.ExpectationGuard.html) is alive. When the guard is dropped, it checks
+//! the number of calls and removes the mock. Unlike in mockall there can be only one expectation
+//! for a function at a time.
+
+use crate::mocking::args::Matcher;
+use crate::mocking::{MockGuard, MockResult, Mockable};
+use std::any::type_name;
+use std::cell::Cell;
+use std::marker::{PhantomData, Tuple};
+use std::rc::Rc;
+use std::thread;
+
+/// Starts building an expectation for the given function
+///
+/// It's an equivalent of mockall's `mock.expect_function()`.
+pub fn expect<T: Tuple, O, F: Mockable<T, O>>(function: F) -> Expectation<T, O, F> {
+    Expectation {
+        function,
+        matcher: None,
+        times: None,
+        phantom: PhantomData,
+    }
+}
+
+/// Builder of an expectation, finished with [returning](#method.returning)
+/// or [return_const](#method.return_const)
+#[must_use = "the expectation is set up only when returning or return_const is called"]
+pub struct Expectation<T, O, F> {
+    function: F,
+    matcher: Option<Box<dyn Matcher<T>>>,
+    times: Option<usize>,
+    phantom: PhantomData<fn() -> O>,
+}
+
+impl<T: Tuple + 'static, O: 'static, F: Mockable<T, O>> Expectation<T, O, F> {
+    /// Accepts only calls with arguments matching the given tuple of matchers,
+    /// any other call panics
+    pub fn with<M: Matcher<T> + 'static>(mut self, matcher: M) -> Self {
+        self.matcher = Some(Box::new(matcher));
+        self
+    }
+
+    /// Expects exactly the given number of calls, any further call panics
+    pub fn times(mut self, count: usize) -> Self {
+        self.times = Some(count);
+        self
+    }
+
+    /// Expects exactly one call
+    pub fn once(self) -> Self {
+        self.times(1)
+    }
+
+    /// Expects no calls
+    pub fn never(self) -> Self {
+        self.times(0)
+    }
+
+    /// Sets up the expectation returning values created from arguments by the given closure
+    pub fn returning<R: FnMut<T, Output = O> + 'static>(self, returning: R) -> ExpectationGuard {
+        let name = type_name::<F>();
+        let calls = Rc::new(Cell::new(0));
+        let mock = ExpectationMock {
+            name,
+            matcher: self.matcher,
+            times: self.times,
+            calls: calls.clone(),
+            returning,
+        };
+        ExpectationGuard {
+            name,
+            times: self.times,
+            calls,
+            _mock_guard: self.function.mock_safe_scoped(mock),
+        }
+    }
+
+    /// Sets up the expectation returning clones of the given value
+    pub fn return_const(self, value: O) -> ExpectationGuard
+    where
+        O: Clone,
+    {
+        self.returning(ConstReturning { value })
+    }
+}
+
+/// Guard of an expectation, which checks the number of calls and removes the mock when dropped
+///
+/// Created by [Expectation](struct.Expectation.html).
+/// Like mocks, it's bound to the thread, where it was created.
+#[must_use = "the expectation is removed as soon as the guard is dropped"]
+pub struct ExpectationGuard {
+    name: &'static str,
+    times: Option<usize>,
+    calls: Rc<Cell<usize>>,
+    _mock_guard: MockGuard,
+}
+
+impl Drop for ExpectationGuard {
+    fn drop(&mut self) {
+        // Do not turn a failing test into an abort
+        if thread::panicking() {
+            return;
+        }
+        if let Some(times) = self.times {
+            let calls = self.calls.get();
+            if calls != times {
+                panic!(
+                    "Mocktopus: expected '{}' to be called exactly {} times, but it was called {} times",
+                    self.name, times, calls
+                );
+            }
+        }
+    }
+}
+
+/// Mock closure checking calls against an expectation
+struct ExpectationMock<T, R> {
+    name: &'static str,
+    matcher: Option<Box<dyn Matcher<T>>>,
+    times: Option<usize>,
+    calls: Rc<Cell<usize>>,
+    returning: R,
+}
+
+impl<T: Tuple, O, R: FnMut<T, Output = O>> FnOnce<T> for ExpectationMock<T, R> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, R: FnMut<T, Output = O>> FnMut<T> for ExpectationMock<T, R> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        if let Some(matcher) = &self.matcher {
+            if !matcher.matches(&input) {
+                panic!(
+                    "Mocktopus: '{}' called with unexpected arguments",
+                    self.name
+                );
+            }
+        }
+        let calls = self.calls.get() + 1;
+        if let Some(times) = self.times {
+            if calls > times {
+                panic!(
+                    "Mocktopus: '{}' called more than {} times",
+                    self.name, times
+                );
+            }
+        }
+        self.calls.set(calls);
+        MockResult::Return(self.returning.call_mut(input))
+    }
+}
+
+/// Closure returning clones of a value, no matter what the arguments are
+struct ConstReturning<O> {
+    value: O,
+}
+
+impl<T: Tuple, O: Clone> FnOnce<T> for ConstReturning<O> {
+    type Output = O;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O: Clone> FnMut<T> for ConstReturning<O> {
+    extern "rust-call" fn call_mut(&mut self, _: T) -> Self::Output {
+        self.value.clone()
+    }
+}
@@ -1,4 +1,5 @@
 #![feature(fn_traits, tuple_trait, unboxed_closures)]
+#![allow(clippy::test_attr_in_doctest)]
 
 //! Mocking framework for Rust (currently only nightly)
 //!
@@ -35,6 +36,26 @@
 //! [dev-dependencies]
 //! mocktopus = "0.7.0"
 //! ```
+//! If Mocktopus is a regular dependency, e.g. because mockable functions are annotated without `cfg_attr`,
+//! `test-only` feature makes sure, that they are made mockable only in test builds. Other builds, like
+//! release or benchmark ones, run them without any mocking overhead. Such functions can be mocked only by
+//! unit tests of their own crate:
+//!
+//! ```
+//! [dependencies]
+//! mocktopus = { version = "0.7.0", features = ["test-only"] }
+//! ```
+//! Mocking can be also turned off completely by disabling default `enabled` feature. Annotated items are
+//! then left untouched and setting up mocks has no effect. Because features of dev-dependencies aren't
+//! enabled in regular builds, it can be turned back on only for tests:
+//!
+//! ```
+//! [dependencies]
+//! mocktopus = { version = "0.7.0", default-features = false }
+//!
+//! [dev-dependencies]
+//! mocktopus = { version = "0.7.0" }
+//! ```
 //! Enable procedural macros in crate root:
 //!
 //! ```
@@ -203,6 +224,17 @@
 //!     assert_eq!(3, my_function_3(1, 1));
 //! }
 //! ```
+//! If the closure returns `MockResult::Panic`, the mocked function panics with message passed inside it:
+//!
+//! ```
+//! #[test]
+//! #[should_panic(expected = "connection lost")]
+//! fn my_function_3_panic_test() {
+//!     my_function_3.mock_safe(|_, _| MockResult::Panic("connection lost".to_string()));
+//!
+//!     my_function_3(1, 1);
+//! }
+//! ```
 //!
 //! ## Mocking generics
 //! When mocking generic functions, all its generics must be defined and only this variant will be affected:
@@ -221,6 +253,47 @@
 //!     assert_eq!("mocked", generic_fn(1u32));
 //! }
 //! ```
+//! Every variant can have its own mock, which is useful when generics appear only in the returned type:
+//!
+//! ```
+//! #[cfg_attr(test, mockable)]
+//! fn parse<T: FromStr>(string: &str) -> Option<T> {
+//!     string.parse().ok()
+//! }
+//!
+//! #[test]
+//! fn parse_test() {
+//!     parse::<i32>.mock_safe(|_| MockResult::Return(Some(1)));
+//!     parse::<f64>.mock_safe(|_| MockResult::Return(Some(2.5)));
+//!
+//!     assert_eq!(Some(1), parse::<i32>("not a number"));
+//!     assert_eq!(Some(2.5), parse::<f64>("not a number"));
+//! }
+//! ```
+//! Closure types can't be named, so a variant accepting a closure can be mocked from a helper,
+//! which infers the closure type from a reference to it.
+//! The mock may ignore the closure, then it's never invoked:
+//!
+//! ```
+//! #[cfg_attr(test, mockable)]
+//! fn retry<F: Fn() -> bool>(attempts: u32, f: F) -> bool {
+//!     (0..attempts).any(|_| f())
+//! }
+//!
+//! fn mock_retry_for<F: Fn() -> bool + 'static>(_: &F) {
+//!     retry::<F>.mock_safe(|_, _| MockResult::Return(true));
+//! }
+//!
+//! #[test]
+//! fn retry_test() {
+//!     let closure = || -> bool { panic!("never called") };
+//!     mock_retry_for(&closure);
+//!
+//!     assert_eq!(true, retry(3, closure));
+//! }
+//! ```
+//! Alternatively the closure can be coerced to a function pointer and `retry::<fn() -> bool>` mocked.
+//!
 //! The only exception are lifetimes, they are ignored:
 //!
 //! ```
@@ -266,7 +339,7 @@
 //! ```
 //! #[cfg_attr(test, mockable)]
 //! async fn sleep(ms: u64) {
-//!     tokio::time::delay_for(std::time::Duration::from_millis(ms)).await;
+//!     tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
 //! }
 //!
 //! #[tokio::test]
@@ -276,6 +349,12 @@
 //!     sleep(10000).await;
 //! }
 //! ```
+//! Values, which are ready immediately, can be returned with
+//! [ready_future](mocking_utils/fn.ready_future.html) helper:
+//!
+//! ```
+//! sleep.mock_safe(|_| MockResult::Return(ready_future(())));
+//! ```
 //!
 //! # Mocking tricks
 //! ## Returning reference to value created inside mock
@@ -333,6 +412,41 @@
 //! }
 //! ```
 //! The vector can store `MockResult`s for more complex mocking.
+//!
+//! For simple cases there is a shorthand, which returns the values one by one and then runs the function normally:
+//!
+//! ```
+//! #[test]
+//! fn my_fn_test() {
+//!     my_fn.mock_returns_seq(vec!["mocked 1".to_string(), "mocked 2".to_string()]);
+//!
+//!     assert_eq!("mocked 1", my_fn());
+//!     assert_eq!("mocked 2", my_fn());
+//!     assert_eq!("not mocked", my_fn());
+//! }
+//! ```
+//!
+//! ## Mocking functions returning `impl Trait`
+//!
+//! Functions returning `impl Trait` are mockable, but the mock must return exactly the same opaque type,
+//! which can't be named or created outside of the function. The mock can still run the function with modified
+//! arguments using `MockResult::Continue` or return a value created by the function itself:
+//!
+//! ```
+//! #[mockable]
+//! fn my_fn(start: u32) -> impl Iterator<Item = u32> {
+//!     start..start + 2
+//! }
+//!
+//! #[test]
+//! fn my_fn_test() {
+//!     my_fn.mock_safe(|start| MockResult::Return(my_fn.call_real((start * 10,))));
+//!
+//!     assert_eq!(vec![10, 11], my_fn(1).collect::<Vec<_>>());
+//! }
+//! ```
+//! If tests need to return arbitrary values, the function must return a nameable type, e.g. a
+//! `Box<dyn Iterator<Item = u32>>`.
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/CodeSandwich/mocktopus/master/logo.png",
     html_favicon_url = "https://raw.githubusercontent.com/CodeSandwich/mocktopus/master/logo.png"
@@ -346,9 +460,104 @@ pub mod mocking;
 /// For use in testing code: helper tools for writing tests using mocking
 pub mod mocking_utils;
 
+/// For use in testing code: adapters for migrating tests from other mocking libraries
+pub mod compat;
+
 /// For use in tested code: tools making items mockable
 pub mod macros {
+    pub use crate::mocked_test;
+    pub use crate::verify;
     pub use mocktopus_macros::*;
 }
 
+/// Asserts how many times a mockable function was called in this thread
+///
+/// The calls are counted like by [call_count](mocking/trait.Mockable.html#tymethod.call_count).
+/// The expected number of calls is given with `times`, `at_least`, `at_most` or `never`.
+/// On failure it panics with the function name and the expected and actual numbers of calls.
+///
+/// ```
+/// #[mockable]
+/// fn get_string() -> String {
+///     "not mocked".to_string()
+/// }
+///
+/// #[test]
+/// fn get_string_test() {
+///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+///
+///     get_string();
+///     get_string();
+///
+///     verify!(get_string, times = 2);
+///     verify!(get_string, at_least = 1);
+///     verify!(get_string, at_most = 3);
+/// }
+/// ```
+#[macro_export]
+macro_rules! verify {
+    ($function:expr, times = $count:expr) => {
+        $crate::verify!(@check $function, "exactly", $count, |actual, count| actual == count)
+    };
+    ($function:expr, at_least = $count:expr) => {
+        $crate::verify!(@check $function, "at least", $count, |actual, count| actual >= count)
+    };
+    ($function:expr, at_most = $count:expr) => {
+        $crate::verify!(@check $function, "at most", $count, |actual, count| actual <= count)
+    };
+    ($function:expr, never) => {
+        $crate::verify!(@check $function, "exactly", 0, |actual, count| actual == count)
+    };
+    (@check $function:expr, $relation:expr, $count:expr, $matches:expr) => {{
+        let actual: usize = $crate::mocking::Mockable::call_count(&$function);
+        let count: usize = $count;
+        if !($matches)(actual, count) {
+            panic!(
+                "Mocktopus: expected '{}' to be called {} {} times, but it was called {} times",
+                stringify!($function),
+                $relation,
+                count,
+                actual
+            );
+        }
+    }};
+}
+
+/// Runs a test body with mockable functions mocked only for its duration
+///
+/// Each `function => mock` pair sets up a mock like
+/// [mock_safe_scoped](mocking/trait.Mockable.html#tymethod.mock_safe_scoped).
+/// All mocks are cleared after the body finishes, even if it panics.
+/// The macro evaluates to the value of the body.
+///
+/// ```
+/// #[mockable]
+/// fn dependency() -> u32 {
+///     1
+/// }
+///
+/// fn caller() -> u32 {
+///     dependency() * 10
+/// }
+///
+/// #[test]
+/// fn caller_test() {
+///     mocked_test!(dependency => || MockResult::Return(5), {
+///         assert_eq!(50, caller());
+///     });
+///
+///     assert_eq!(10, caller());
+/// }
+/// ```
+#[macro_export]
+macro_rules! mocked_test {
+    ($body:block) => {
+        $body
+    };
+    ($function:expr => $mock:expr, $($rest:tt)+) => {{
+        let _guard = $crate::mocking::Mockable::mock_safe_scoped(&$function, $mock);
+        $crate::mocked_test!($($rest)+)
+    }};
+}
+
 mod mock_store;
@@ -1,12 +1,60 @@
-use crate::mocking::MockResult;
-use std::{any::TypeId, marker::Tuple};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use crate::mocking::{FnTiming, MockResult};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::mem::transmute;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use std::{any::TypeId, marker::Tuple};
+
+/// Key under which a mock is stored
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum MockId {
+    /// Mockable function identified by its unique ID
+    Fn(TypeId),
+    /// Mockable closure identified by user-provided key and unique ID of its signature
+    Key(String, TypeId),
+    /// Trait method of all implementors identified by qualified name of the method
+    /// and unique ID of its signature without receiver
+    TraitMethod(Cow<'static, str>, TypeId),
+    /// Mockable function identified by its fully qualified name and unique ID of its signature
+    Name(String, TypeId),
+    /// All instantiations of a generic mockable function identified by its fully qualified name
+    AllInstantiations(Cow<'static, str>),
+}
+
+/// Mock, which returns input back if it doesn't handle it
+pub type ConditionalMock<I, O> = Box<dyn FnMut<I, Output = Result<MockResult<I, O>, I>>>;
+
+type ConditionalMocks<I, O> = RefCell<Vec<Rc<RefCell<ConditionalMock<I, O>>>>>;
 
 pub struct MockStore {
     layers: RefCell<Vec<MockLayer>>,
+    mocks_required: Cell<bool>,
+    /// Functions, which next call should run normally, bypassing mocks
+    real_calls: RefCell<HashSet<MockId>>,
+    call_counts: RefCell<HashMap<MockId, usize>>,
+    /// Human readable names of functions, which had mocks set up
+    fn_names: RefCell<HashMap<MockId, &'static str>>,
+    timing_enabled: Cell<bool>,
+    recording_calls: Cell<bool>,
+    /// Names of called functions in order of calls made while recording was enabled
+    call_sequence: RefCell<Vec<&'static str>>,
+    /// Durations of real calls measured while timing was enabled
+    timings: RefCell<HashMap<&'static str, FnTiming>>,
+    /// Conditional mocks, which are currently set up in thread layer
+    conditional_mocks: RefCell<HashMap<MockId, Weak<ConditionalMocks<(), ()>>>>,
+    /// Set when mock identified by name is added, lets calls skip building name IDs when there are none
+    name_mocks_added: Cell<bool>,
+    /// Set when mock of all instantiations is added, lets calls skip building their IDs when there are none
+    all_instantiations_mocks_added: Cell<bool>,
+    /// Set when trait method mock is added, lets calls skip building their IDs when there are none
+    trait_method_mocks_added: Cell<bool>,
+    /// Set when any mock is added, calls are recorded or mocks are required, otherwise calls of mockable
+    /// functions skip all bookkeeping, including counting
+    tracking_calls: Cell<bool>,
 }
 
 impl MockStore {
@@ -14,9 +62,17 @@ impl MockStore {
         for layer in self.layers.borrow_mut().iter_mut() {
             layer.clear()
         }
+        self.call_counts.borrow_mut().clear();
+        self.real_calls.borrow_mut().clear();
+        self.conditional_mocks.borrow_mut().clear();
+        self.name_mocks_added.set(false);
+        self.all_instantiations_mocks_added.set(false);
+        self.trait_method_mocks_added.set(false);
+        self.tracking_calls
+            .set(self.recording_calls.get() || self.mocks_required.get());
     }
 
-    pub fn clear_id(&self, id: TypeId) {
+    pub fn clear_id(&self, id: &MockId) {
         for layer in self.layers.borrow_mut().iter_mut() {
             layer.clear_id(id)
         }
@@ -25,6 +81,7 @@ impl MockStore {
     /// Layer will be in use as long as MockLayerGuard is alive
     /// MockLayerGuards must always be dropped and always in reverse order of their creation
     pub unsafe fn add_layer(&self, layer: MockLayer) {
+        self.tracking_calls.set(true);
         self.layers.borrow_mut().push(layer)
     }
 
@@ -34,9 +91,16 @@ impl MockStore {
 
     pub unsafe fn add_to_thread_layer<I: Tuple, O>(
         &self,
-        id: TypeId,
+        id: MockId,
         mock: Box<dyn FnMut<I, Output = MockResult<I, O>> + 'static>,
     ) {
+        match id {
+            MockId::Name(..) => self.name_mocks_added.set(true),
+            MockId::AllInstantiations(_) => self.all_instantiations_mocks_added.set(true),
+            MockId::TraitMethod(..) => self.trait_method_mocks_added.set(true),
+            _ => (),
+        }
+        self.tracking_calls.set(true);
         self.layers
             .borrow_mut()
             .first_mut()
@@ -44,7 +108,217 @@ impl MockStore {
             .add(id, mock);
     }
 
-    pub unsafe fn call<I: Tuple, O>(&self, id: TypeId, mut input: I) -> MockResult<I, O> {
+    /// Adds mock after all conditional mocks already set up in thread layer.
+    /// If there are none, replaces any other mock set up in thread layer.
+    pub unsafe fn add_conditional_to_thread_layer<I: Tuple, O>(
+        &self,
+        id: MockId,
+        mock: ConditionalMock<I, O>,
+    ) {
+        let existing = self
+            .conditional_mocks
+            .borrow()
+            .get(&id)
+            .and_then(Weak::upgrade);
+        match existing {
+            Some(mocks) => {
+                let mocks =
+                    transmute::<Rc<ConditionalMocks<(), ()>>, Rc<ConditionalMocks<I, O>>>(mocks);
+                mocks.borrow_mut().push(Rc::new(RefCell::new(mock)));
+            }
+            None => {
+                let mocks = Rc::new(RefCell::new(vec![Rc::new(RefCell::new(mock))]));
+                let weak = transmute::<Weak<ConditionalMocks<I, O>>, Weak<ConditionalMocks<(), ()>>>(
+                    Rc::downgrade(&mocks),
+                );
+                self.conditional_mocks.borrow_mut().insert(id.clone(), weak);
+                let caller =
+                    Box::new(ConditionalMocksCaller { mocks }) as Box<dyn FnMut<_, Output = _>>;
+                let static_caller: Box<dyn FnMut<I, Output = MockResult<I, O>> + 'static> =
+                    transmute(caller);
+                self.add_to_thread_layer(id, static_caller);
+            }
+        }
+    }
+
+    /// Removes mock from thread layer, so it can be put back with `restore_to_thread_layer`
+    pub fn take_from_thread_layer(&self, id: &MockId) -> TakenMock {
+        let mock = self
+            .layers
+            .borrow_mut()
+            .first_mut()
+            .expect("Thread mock level missing")
+            .mocks
+            .remove(id);
+        let conditional_mocks = self.conditional_mocks.borrow_mut().remove(id);
+        TakenMock {
+            mock,
+            conditional_mocks,
+        }
+    }
+
+    /// Replaces mock in thread layer with the taken one
+    pub fn restore_to_thread_layer(&self, id: &MockId, taken: TakenMock) {
+        let mut layers = self.layers.borrow_mut();
+        let mocks = &mut layers.first_mut().expect("Thread mock level missing").mocks;
+        match taken.mock {
+            Some(mock) => mocks.insert(id.clone(), mock),
+            None => mocks.remove(id),
+        };
+        let mut conditional_mocks = self.conditional_mocks.borrow_mut();
+        match taken.conditional_mocks {
+            Some(weak) => conditional_mocks.insert(id.clone(), weak),
+            None => conditional_mocks.remove(id),
+        };
+    }
+
+    /// False if no mock identified by name could be set up
+    pub fn name_mocks_added(&self) -> bool {
+        self.name_mocks_added.get()
+    }
+
+    /// False if no mock of all instantiations could be set up
+    pub fn all_instantiations_mocks_added(&self) -> bool {
+        self.all_instantiations_mocks_added.get()
+    }
+
+    /// False if no trait method mock could be set up
+    pub fn trait_method_mocks_added(&self) -> bool {
+        self.trait_method_mocks_added.get()
+    }
+
+    pub fn tracking_calls(&self) -> bool {
+        self.tracking_calls.get()
+    }
+
+    pub fn has_mock(&self, id: &MockId) -> bool {
+        self.layers.borrow().iter().any(|layer| layer.has(id))
+    }
+
+    pub fn set_fn_name(&self, id: MockId, name: &'static str) {
+        self.fn_names.borrow_mut().insert(id, name);
+    }
+
+    /// Names of all mocked items in all layers, sorted and without duplicates
+    pub fn active_mock_names(&self) -> Vec<String> {
+        let fn_names = self.fn_names.borrow();
+        let mut names: Vec<String> = self
+            .layers
+            .borrow()
+            .iter()
+            .flat_map(|layer| layer.mocks.keys())
+            .map(|id| match id {
+                MockId::Fn(_) => fn_names.get(id).copied().unwrap_or("<unknown>").to_string(),
+                MockId::Key(key, _) => key.clone(),
+                MockId::TraitMethod(trait_method, _) => trait_method.to_string(),
+                MockId::Name(name, _) => name.clone(),
+                MockId::AllInstantiations(name) => name.to_string(),
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn mocks_required(&self) -> bool {
+        self.mocks_required.get()
+    }
+
+    pub fn set_mocks_required(&self, required: bool) {
+        self.mocks_required.set(required);
+        if required {
+            self.tracking_calls.set(true)
+        }
+    }
+
+    pub fn timing_enabled(&self) -> bool {
+        self.timing_enabled.get()
+    }
+
+    pub fn set_timing_enabled(&self, enabled: bool) {
+        self.timing_enabled.set(enabled)
+    }
+
+    pub fn clear_timings(&self) {
+        self.timings.borrow_mut().clear()
+    }
+
+    pub fn record_timing(&self, name: &'static str, duration: Duration) {
+        let mut timings = self.timings.borrow_mut();
+        let timing = timings.entry(name).or_insert(FnTiming {
+            name,
+            calls: 0,
+            total: Duration::ZERO,
+            min: duration,
+            max: duration,
+        });
+        timing.calls += 1;
+        timing.total += duration;
+        timing.min = timing.min.min(duration);
+        timing.max = timing.max.max(duration);
+    }
+
+    /// Returns timings sorted by fn name
+    pub fn timings(&self) -> Vec<FnTiming> {
+        let mut timings: Vec<_> = self.timings.borrow().values().cloned().collect();
+        timings.sort_by_key(|timing| timing.name);
+        timings
+    }
+
+    pub fn set_recording_calls(&self, recording: bool) {
+        self.recording_calls.set(recording);
+        if recording {
+            self.tracking_calls.set(true)
+        }
+    }
+
+    pub fn clear_call_sequence(&self) {
+        self.call_sequence.borrow_mut().clear()
+    }
+
+    pub fn record_call(&self, name: &'static str) {
+        if self.recording_calls.get() {
+            self.call_sequence.borrow_mut().push(name)
+        }
+    }
+
+    pub fn call_sequence(&self) -> Vec<String> {
+        self.call_sequence
+            .borrow()
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    pub fn set_real_call(&self, id: MockId) {
+        self.real_calls.borrow_mut().insert(id);
+    }
+
+    pub fn has_real_call(&self, id: &MockId) -> bool {
+        self.real_calls.borrow().contains(id)
+    }
+
+    /// Returns true and unsets real call flag if it was set
+    pub fn take_real_call(&self, id: &MockId) -> bool {
+        self.real_calls.borrow_mut().remove(id)
+    }
+
+    pub fn call_count(&self, id: &MockId) -> usize {
+        self.call_counts.borrow().get(id).copied().unwrap_or(0)
+    }
+
+    /// Starts tracking calls, so they are counted from now on
+    pub fn reset_call_count(&self, id: &MockId) {
+        self.tracking_calls.set(true);
+        self.call_counts.borrow_mut().remove(id);
+    }
+
+    pub fn count_call(&self, id: &MockId) {
+        *self.call_counts.borrow_mut().entry(id.clone()).or_insert(0) += 1;
+    }
+
+    pub unsafe fn call<I: Tuple, O>(&self, id: &MockId, mut input: I) -> MockResult<I, O> {
+        self.count_call(id);
         // Do not hold RefCell borrow while calling mock, it can try to modify mocks
         let layer_count = self.layers.borrow().len();
         for layer_idx in (0..layer_count).rev() {
@@ -75,13 +349,32 @@ impl Default for MockStore {
     fn default() -> Self {
         MockStore {
             layers: RefCell::new(vec![MockLayer::default()]),
+            mocks_required: Cell::new(false),
+            real_calls: RefCell::new(HashSet::new()),
+            call_counts: RefCell::new(HashMap::new()),
+            fn_names: RefCell::new(HashMap::new()),
+            timing_enabled: Cell::new(false),
+            recording_calls: Cell::new(false),
+            call_sequence: RefCell::new(Vec::new()),
+            timings: RefCell::new(HashMap::new()),
+            conditional_mocks: RefCell::new(HashMap::new()),
+            name_mocks_added: Cell::new(false),
+            all_instantiations_mocks_added: Cell::new(false),
+            trait_method_mocks_added: Cell::new(false),
+            tracking_calls: Cell::new(false),
         }
     }
 }
 
+/// Mock taken out of thread layer together with its conditional mocks tracking
+pub struct TakenMock {
+    mock: Option<ErasedStoredMock>,
+    conditional_mocks: Option<Weak<ConditionalMocks<(), ()>>>,
+}
+
 #[derive(Default)]
 pub struct MockLayer {
-    mocks: HashMap<TypeId, ErasedStoredMock>,
+    mocks: HashMap<MockId, ErasedStoredMock>,
 }
 
 impl MockLayer {
@@ -89,21 +382,25 @@ impl MockLayer {
         self.mocks.clear()
     }
 
-    fn clear_id(&mut self, id: TypeId) {
-        self.mocks.remove(&id);
+    fn clear_id(&mut self, id: &MockId) {
+        self.mocks.remove(id);
     }
 
     pub unsafe fn add<I: Tuple, O>(
         &mut self,
-        id: TypeId,
+        id: MockId,
         mock: Box<dyn FnMut<I, Output = MockResult<I, O>> + 'static>,
     ) {
         let stored = StoredMock::new(mock).erase();
         self.mocks.insert(id, stored);
     }
 
-    unsafe fn get(&self, id: TypeId) -> Option<ErasedStoredMock> {
-        self.mocks.get(&id).cloned()
+    fn has(&self, id: &MockId) -> bool {
+        self.mocks.contains_key(id)
+    }
+
+    unsafe fn get(&self, id: &MockId) -> Option<ErasedStoredMock> {
+        self.mocks.get(id).cloned()
     }
 }
 
@@ -148,8 +445,175 @@ impl<I: Tuple, O> StoredMock<I, O> {
     fn erase(self) -> ErasedStoredMock {
         unsafe {
             ErasedStoredMock {
-                mock: transmute(self),
+                mock: transmute::<StoredMock<I, O>, StoredMock<(), ()>>(self),
             }
         }
     }
 }
+
+type ErasedGlobalMock = Arc<GlobalMock>;
+
+struct GlobalMock {
+    /// Set only for mocks handling a single call, raised by the thread, which takes the call
+    taken: Option<AtomicBool>,
+    mock: Mutex<Box<dyn FnMut<(), Output = MockResult<(), ()>> + Send>>,
+}
+
+thread_local! {
+    /// Global mocks, which are currently running in this thread
+    static RUNNING_GLOBAL_MOCKS: RefCell<HashSet<MockId>> = RefCell::new(HashSet::new())
+}
+
+/// Store of mocks shared by all threads
+#[derive(Default)]
+pub struct GlobalMockStore {
+    mocks: Mutex<HashMap<MockId, ErasedGlobalMock>>,
+    /// Number of stored mocks, lets calls skip locking the store when there are none
+    mock_count: AtomicUsize,
+}
+
+impl GlobalMockStore {
+    pub fn clear(&self) {
+        let mut mocks = self.lock_mocks();
+        mocks.clear();
+        self.update_mock_count(&mocks);
+    }
+
+    pub unsafe fn add<I: Tuple, O>(
+        &self,
+        id: MockId,
+        mock: Box<dyn FnMut<I, Output = MockResult<I, O>> + Send + 'static>,
+    ) {
+        self.add_erased(id, mock, None)
+    }
+
+    /// Adds mock, which is called only by the first thread calling the function.
+    /// Other calls, including the ones racing with the first one, are not handled.
+    pub unsafe fn add_once<I: Tuple, O>(
+        &self,
+        id: MockId,
+        mock: Box<dyn FnMut<I, Output = MockResult<I, O>> + Send + 'static>,
+    ) {
+        self.add_erased(id, mock, Some(AtomicBool::new(false)))
+    }
+
+    unsafe fn add_erased<I: Tuple, O>(
+        &self,
+        id: MockId,
+        mock: Box<dyn FnMut<I, Output = MockResult<I, O>> + Send + 'static>,
+        taken: Option<AtomicBool>,
+    ) {
+        let erased = transmute::<
+            Box<dyn FnMut<I, Output = MockResult<I, O>> + Send>,
+            Box<dyn FnMut<(), Output = MockResult<(), ()>> + Send>,
+        >(mock);
+        let mock = GlobalMock {
+            taken,
+            mock: Mutex::new(erased),
+        };
+        let mut mocks = self.lock_mocks();
+        mocks.insert(id, Arc::new(mock));
+        self.update_mock_count(&mocks);
+    }
+
+    pub fn has_mock(&self, id: &MockId) -> bool {
+        self.has_any_mock() && self.lock_mocks().contains_key(id)
+    }
+
+    /// Calls mock if it's set up and it's not already running in this thread.
+    /// If it's running in other thread, waits for it to finish.
+    pub unsafe fn call<I: Tuple, O>(&self, id: &MockId, input: I) -> MockLayerResult<I, O> {
+        if !self.has_any_mock() {
+            return MockLayerResult::Unhandled(input);
+        }
+        // Do not hold store lock while calling mock, it can try to modify mocks
+        let mock = match self.lock_mocks().get(id) {
+            Some(mock) => mock.clone(),
+            None => return MockLayerResult::Unhandled(input),
+        };
+        if !RUNNING_GLOBAL_MOCKS.with(|running| running.borrow_mut().insert(id.clone())) {
+            return MockLayerResult::Unhandled(input);
+        }
+        let _running_guard = RunningGlobalMockGuard { id };
+        if let Some(taken) = &mock.taken {
+            // Exactly one thread swaps false to true, others don't even wait for mock lock
+            if taken.swap(true, Ordering::AcqRel) {
+                return MockLayerResult::Unhandled(input);
+            }
+            let mut mocks = self.lock_mocks();
+            if mocks
+                .get(id)
+                .is_some_and(|stored| Arc::ptr_eq(stored, &mock))
+            {
+                mocks.remove(id);
+                self.update_mock_count(&mocks);
+            }
+        }
+        let mut erased = mock.mock.lock().unwrap_or_else(PoisonError::into_inner);
+        let unerased = transmute::<
+            &mut Box<dyn FnMut<(), Output = MockResult<(), ()>> + Send>,
+            &mut Box<dyn FnMut<I, Output = MockResult<I, O>> + Send>,
+        >(&mut *erased);
+        MockLayerResult::Handled(unerased.call_mut(input))
+    }
+
+    pub fn has_any_mock(&self) -> bool {
+        self.mock_count.load(Ordering::Acquire) != 0
+    }
+
+    /// Must be called with the store lock held after every change of the stored mocks
+    fn update_mock_count(&self, mocks: &HashMap<MockId, ErasedGlobalMock>) {
+        self.mock_count.store(mocks.len(), Ordering::Release)
+    }
+
+    fn lock_mocks(&self) -> std::sync::MutexGuard<'_, HashMap<MockId, ErasedGlobalMock>> {
+        // Mocks often panic on failed assertions, it must not break other tests
+        self.mocks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Marks global mock as no longer running in this thread, even if it panics
+struct RunningGlobalMockGuard<'a> {
+    id: &'a MockId,
+}
+
+impl Drop for RunningGlobalMockGuard<'_> {
+    fn drop(&mut self) {
+        RUNNING_GLOBAL_MOCKS.with(|running| running.borrow_mut().remove(self.id));
+    }
+}
+
+/// Calls conditional mocks in order until one of them handles input
+struct ConditionalMocksCaller<I, O> {
+    mocks: Rc<ConditionalMocks<I, O>>,
+}
+
+impl<I: Tuple, O> FnOnce<I> for ConditionalMocksCaller<I, O> {
+    type Output = MockResult<I, O>;
+
+    extern "rust-call" fn call_once(mut self, input: I) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<I: Tuple, O> FnMut<I> for ConditionalMocksCaller<I, O> {
+    extern "rust-call" fn call_mut(&mut self, mut input: I) -> Self::Output {
+        // The list isn't borrowed while a mock runs, so the mock can set up more conditional mocks
+        for index in 0.. {
+            let mock = match self.mocks.borrow().get(index) {
+                Some(mock) => mock.clone(),
+                None => break,
+            };
+            // Mock is already running if it calls its function again
+            let mut mock = match mock.try_borrow_mut() {
+                Ok(mock) => mock,
+                Err(_) => continue,
+            };
+            match mock.call_mut(input) {
+                Ok(result) => return result,
+                Err(unhandled) => input = unhandled,
+            }
+        }
+        MockResult::Continue(input)
+    }
+}
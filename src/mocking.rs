@@ -1,13 +1,48 @@
-use crate::mock_store::{MockLayer, MockStore};
-use std::{any::{Any, TypeId}, marker::Tuple};
+pub mod args;
+
+use self::args::Matcher;
+use crate::mock_store::{
+    ConditionalMock, GlobalMockStore, MockId, MockLayer, MockLayerResult, MockStore, TakenMock,
+};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
 use std::mem::transmute;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::{Rc, Weak};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use std::{
+    any::{type_name, Any, TypeId},
+    marker::Tuple,
+};
 
 /// Trait for setting up mocks
 ///
 /// The trait is implemented for all functions, so its methods can be called on any function.
 ///
 /// Note: methods have any effect only if called on functions [annotated as mockable](https://docs.rs/mocktopus_macros).
+///
+/// The type parameters are the function's arguments tuple and return type, so mock closures with different
+/// arguments or returning values of a different type don't compile:
+///
+/// ```
+/// #[mockable]
+/// fn get_string(id: u32) -> String {
+///     "not mocked".to_string()
+/// }
+///
+/// #[test]
+/// fn get_string_test() {
+///     get_string.mock_safe(|id| MockResult::Return(id)); // error: expected `String`, found `u32`
+/// }
+/// ```
+///
+/// Mocks of generic functions are identified by the instantiated types, so they have effect on the same
+/// instantiation no matter from which module or crate it's called. All crates must use the same version
+/// of Mocktopus though, different versions keep mocks in separate stores.
 pub trait Mockable<T: Tuple, O> {
     /// Core function for setting up mocks
     ///
@@ -48,6 +83,10 @@ pub trait Mockable<T: Tuple, O> {
     ///
     /// The safety is guaranteed by forcing passed closure to be static.
     /// This eliminates the problem of using non-static values, which may not live long enough.
+    /// The closure is `FnMut`, so it can own and modify its state directly, e.g. count calls in a captured variable,
+    /// without `Cell` or `RefCell`.
+    /// The closure may be called many times, so it can't move its captured state out into the returned value.
+    /// To return owned data on every call it must create a new value each time, e.g. by cloning the captured one.
     ///
     /// ```
     /// #[mockable]
@@ -64,65 +103,2346 @@ pub trait Mockable<T: Tuple, O> {
     /// ```
     fn mock_safe<M: FnMut<T, Output = MockResult<T, O>> + 'static>(&self, mock: M);
 
-    /// Stop mocking this function.
+    /// A variant of [mock_safe](#tymethod.mock_safe), which accepts an already boxed mock closure
     ///
-    /// All future invocations will be forwarded to the real implementation.
-    fn clear_mock(&self);
+    /// The box is stored as is, so closures built at runtime, e.g. from test data, can be used as mocks
+    /// without being boxed again. A boxed `Fn` closure is accepted too, because it coerces into a boxed `FnMut`.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string(arg: u32) -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     let canned = Some("mocked".to_string());
+    ///     let mock: Box<dyn Fn(u32) -> MockResult<(u32,), String>> = match canned {
+    ///         Some(text) => Box::new(move |_| MockResult::Return(text.clone())),
+    ///         None => Box::new(|arg| MockResult::Continue((arg,))),
+    ///     };
+    ///     get_string.mock_safe_boxed(mock);
+    ///
+    ///     assert_eq!("mocked", get_string(1));
+    /// }
+    /// ```
+    fn mock_safe_boxed(&self, mock: Box<dyn FnMut<T, Output = MockResult<T, O>>>);
 
-    #[doc(hidden)]
-    /// Called before every execution of a mockable function. Checks if mock is set and if it is, calls it.
-    fn call_mock(&self, input: T) -> MockResult<T, O>;
+    /// A variant of [mock_safe](#tymethod.mock_safe) for fallible mock closures
+    ///
+    /// The mock closure returns a `Result`, so it can use `?` operator. If it returns an error,
+    /// the mocked function panics with the error message.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_number() -> u32 {
+    ///     0
+    /// }
+    ///
+    /// #[test]
+    /// fn get_number_test() {
+    ///     get_number.try_mock_safe(|| Ok(MockResult::Return("1".parse::<u32>()?)));
+    ///
+    ///     assert_eq!(1, get_number());
+    /// }
+    /// ```
+    fn try_mock_safe<E, M>(&self, mock: M)
+    where
+        E: Display + 'static,
+        O: 'static,
+        M: FnMut<T, Output = Result<MockResult<T, O>, E>> + 'static;
 
-    #[doc(hidden)]
-    /// Returns a unique ID of the function, which is used to set and get its mock.
-    unsafe fn get_mock_id(&self) -> TypeId;
-}
+    /// A variant of [mock_safe](#tymethod.mock_safe), which mocks only a limited number of calls
+    ///
+    /// The mock closure is called only for the next `count` calls of the mocked function.
+    /// All further calls run normally, as if the mock returned `MockResult::Continue` with unchanged arguments.
+    /// Setting up a new mock for the function discards the remaining count.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe_times(2, || MockResult::Return("mocked".to_string()));
+    ///
+    ///     assert_eq!("mocked", get_string());
+    ///     assert_eq!("mocked", get_string());
+    ///     assert_eq!("not mocked", get_string());
+    /// }
+    /// ```
+    fn mock_safe_times<M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+        &self,
+        count: usize,
+        mock: M,
+    );
 
-/// Controls mocked function behavior when returned from [mock closure](trait.Mockable.html)
-pub enum MockResult<T, O> {
-    /// Function runs normally as if it was called with given arguments.
-    /// The arguments are passed inside enum variant as a tuple.
-    Continue(T),
+    /// A variant of [mock_safe](#tymethod.mock_safe), which mocks only the next call
+    ///
+    /// The mock closure is called only once, all further calls run normally. Unlike
+    /// [mock_safe_times](#tymethod.mock_safe_times) it accepts `FnOnce` closures, so it can move captured values out.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     let mocked = "mocked".to_string();
+    ///     get_string.mock_safe_once(move || MockResult::Return(mocked));
+    ///
+    ///     assert_eq!("mocked", get_string());
+    ///     assert_eq!("not mocked", get_string());
+    ///     assert_eq!(2, get_string.call_count());
+    /// }
+    /// ```
+    fn mock_safe_once<M: FnOnce<T, Output = MockResult<T, O>> + 'static>(&self, mock: M);
 
-    /// Function returns immediately with a given value. The returned value is passed inside enum variant.
-    Return(O),
-}
+    /// A variant of [mock_safe](#tymethod.mock_safe), which doesn't mock calls made while the function
+    /// is already executing
+    ///
+    /// The mock is used only for the outermost call. If it returns `Continue`, the function runs normally
+    /// and all calls it makes to itself, directly or indirectly, run normally too. It's useful for
+    /// recursive functions, which should be mocked only at the entry point.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn factorial(n: u64) -> u64 {
+    ///     if n <= 1 { 1 } else { n * factorial(n - 1) }
+    /// }
+    ///
+    /// #[test]
+    /// fn factorial_test() {
+    ///     factorial.mock_safe_non_reentrant(|n| MockResult::Continue((n + 1,)));
+    ///
+    ///     assert_eq!(24, factorial(3));
+    /// }
+    /// ```
+    fn mock_safe_non_reentrant<M: FnMut<T, Output = MockResult<T, O>> + 'static>(&self, mock: M)
+    where
+        Self: Clone + 'static,
+        O: 'static;
 
-thread_local! {
-    static MOCK_STORE: MockStore = MockStore::default()
-}
+    /// Mocks the function to run normally and return its result transformed by the given closure
+    ///
+    /// Every call runs the real function body like [call_real](#tymethod.call_real), then the returned
+    /// value is passed to the map closure and the mocked function returns whatever the closure returns.
+    /// Like with any other mock, calls the function makes to itself while the mock is running aren't mocked,
+    /// so in recursive functions only the outermost result gets transformed. It's useful for fault injection,
+    /// e.g. corrupting a single field of an otherwise real value.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_map(|result| result.to_uppercase());
+    ///
+    ///     assert_eq!("NOT MOCKED", get_string());
+    /// }
+    /// ```
+    fn mock_map<G: FnMut(O) -> O + 'static>(&self, map: G)
+    where
+        Self: Clone + 'static;
 
-/// Clear all mocks in the ThreadLocal; only necessary if tests share threads
-pub fn clear_mocks() {
-    MOCK_STORE.with(|mock_store| mock_store.clear())
-}
+    /// Mocks the function to run normally with arguments replaced by the given closure
+    ///
+    /// Before every call the map closure receives the arguments and returns a tuple of arguments, with which
+    /// the function body runs. It's a shorthand for [mock_safe](#tymethod.mock_safe) with a closure returning
+    /// `MockResult::Continue`, so the returned arguments replace the original ones the same way, e.g. values
+    /// modified through `&mut` arguments stay modified. Unlike with [mock_map](#tymethod.mock_map) the mock
+    /// isn't running when the body runs, so recursive calls are mocked too. It's useful for sanitizing inputs,
+    /// e.g. clamping them to the range the test cares about.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string(id: i32) -> String {
+    ///     format!("not mocked {}", id)
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_map_args(|id| (id.max(0),));
+    ///
+    ///     assert_eq!("not mocked 0", get_string(-1));
+    /// }
+    /// ```
+    fn mock_map_args<G: FnMut<T, Output = T> + 'static>(&self, map: G)
+    where
+        O: 'static;
 
-impl<T: Tuple, O, F: FnOnce<T, Output = O>> Mockable<T, O> for F {
-    unsafe fn mock_raw<M: FnMut<T, Output = MockResult<T, O>>>(&self, mock: M) {
-        let id = self.get_mock_id();
-        let boxed = Box::new(mock) as Box<dyn FnMut<_, Output = _>>;
-        let static_boxed: Box<dyn FnMut<T, Output = MockResult<T, O>> + 'static> = transmute(boxed);
-        MOCK_STORE.with(|mock_store| mock_store.add_to_thread_layer(id, static_boxed))
-    }
+    /// Mocks the function to forward all calls to the delegate and return its result
+    ///
+    /// The delegate must accept the same arguments and return the same type as the mocked function,
+    /// it's usually another function, e.g. a new implementation of an API being replaced.
+    /// If the delegate is mockable, its mocks are used as usual.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn old_get_string(id: u32) -> String {
+    ///     "old".to_string()
+    /// }
+    ///
+    /// fn new_get_string(id: u32) -> String {
+    ///     "new".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     old_get_string.mock_delegate_to(new_get_string);
+    ///
+    ///     assert_eq!("new", old_get_string(1));
+    /// }
+    /// ```
+    fn mock_delegate_to<G: FnMut<T, Output = O> + 'static>(&self, delegate: G);
 
-    fn mock_safe<M: FnMut<T, Output = MockResult<T, O>> + 'static>(&self, mock: M) {
-        unsafe { self.mock_raw(mock) }
-    }
+    /// A variant of [mock_safe](#tymethod.mock_safe), which mocks only calls with matching arguments
+    ///
+    /// Before every call of the mocked function the matcher closure receives a reference to the arguments tuple.
+    /// If it returns `true`, the mock closure is called, otherwise the function runs normally.
+    ///
+    /// Multiple mocks can be set up this way for a single function. They are tried in order in which
+    /// they were set up and the first one with a matching matcher is called. Setting up the first of them replaces
+    /// any mock set up with other methods and setting up a mock with other methods replaces all of them.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string(id: u32) -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe_when(|&(id,)| id == 1, |_| MockResult::Return("mocked 1".to_string()));
+    ///     get_string.mock_safe_when(|&(id,)| id < 3, |_| MockResult::Return("mocked 2".to_string()));
+    ///
+    ///     assert_eq!("mocked 1", get_string(1));
+    ///     assert_eq!("mocked 2", get_string(2));
+    ///     assert_eq!("not mocked", get_string(3));
+    /// }
+    /// ```
+    fn mock_safe_when<P, M>(&self, matcher: P, mock: M)
+    where
+        P: FnMut(&T) -> bool + 'static,
+        M: FnMut<T, Output = MockResult<T, O>> + 'static;
 
-    fn clear_mock(&self) {
-        let id = unsafe { self.get_mock_id() };
-        MOCK_STORE.with(|mock_store| mock_store.clear_id(id))
-    }
+    /// Creates a builder of a mock with multiple branches for calls with different arguments
+    ///
+    /// Every branch consists of a matcher closure passed to [when](struct.MockBuilder.html#method.when)
+    /// and a mock closure passed to [then](struct.MockBuilderWhen.html#method.then).
+    /// The mock is set up when the builder is [installed](struct.MockBuilder.html#method.install).
+    /// Before every call of the mocked function the branches are tried in order in which they were added
+    /// and the mock closure of the first one with a matching matcher is called.
+    /// If none matches, the function runs normally.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string(id: u32) -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string
+    ///         .mock_builder()
+    ///         .when(|&(id,)| id == 1)
+    ///         .then(|_| MockResult::Return("mocked 1".to_string()))
+    ///         .when(|&(id,)| id == 2)
+    ///         .then(|_| MockResult::Return("mocked 2".to_string()))
+    ///         .install();
+    ///
+    ///     assert_eq!("mocked 1", get_string(1));
+    ///     assert_eq!("mocked 2", get_string(2));
+    ///     assert_eq!("not mocked", get_string(3));
+    /// }
+    /// ```
+    fn mock_builder(&self) -> MockBuilder<T, O>;
 
-    fn call_mock(&self, input: T) -> MockResult<T, O> {
-        unsafe {
-            let id = self.get_mock_id();
-            MOCK_STORE.with(|mock_store| mock_store.call(id, input))
-        }
-    }
+    /// Sets up a mock, which returns given values one by one
+    ///
+    /// Every call of the mocked function returns the next value in the order, in which they were given.
+    /// When all values are used up, the function runs normally.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_returns_seq(vec!["mocked 1".to_string(), "mocked 2".to_string()]);
+    ///
+    ///     assert_eq!("mocked 1", get_string());
+    ///     assert_eq!("mocked 2", get_string());
+    ///     assert_eq!("not mocked", get_string());
+    /// }
+    /// ```
+    fn mock_returns_seq(&self, values: Vec<O>)
+    where
+        O: 'static;
 
-    unsafe fn get_mock_id(&self) -> TypeId {
-        (|| ()).type_id()
+    /// Sets up a mock, which records arguments of all calls and lets the function run normally
+    ///
+    /// The arguments are cloned and stored in the returned [CallRecorder](struct.CallRecorder.html).
+    /// The recorded calls are available as long as the spy is set up. Setting up a new mock or clearing mocks
+    /// of the function discards them.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string(id: u32) -> String {
+    ///     format!("not mocked {}", id)
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     let recorder = get_string.spy();
+    ///
+    ///     assert_eq!("not mocked 1", get_string(1));
+    ///     assert_eq!("not mocked 2", get_string(2));
+    ///
+    ///     assert_eq!(vec![(1,), (2,)], recorder.calls());
+    /// }
+    /// ```
+    fn spy(&self) -> CallRecorder<T>
+    where
+        T: Clone + 'static,
+        O: 'static;
+
+    /// Sets up a mock, which lets the inspector closure observe arguments of all calls and lets the function
+    /// run normally
+    ///
+    /// The inspector receives a reference to the arguments tuple, so unlike [spy](#tymethod.spy) it doesn't
+    /// require them to be `Clone` and the function always receives back the very same values.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string(name: String) -> String {
+    ///     format!("not mocked {}", name)
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.inspect(|(name,)| println!("called with {}", name));
+    ///
+    ///     assert_eq!("not mocked abc", get_string("abc".to_string()));
+    /// }
+    /// ```
+    fn inspect<I: FnMut(&T) + 'static>(&self, inspector: I)
+    where
+        O: 'static;
+
+    /// A variant of [mock_safe](#tymethod.mock_safe), which sets up a mock removed when the returned guard is dropped
+    ///
+    /// Dropping the [MockGuard](struct.MockGuard.html) works like calling [clear_mock](#tymethod.clear_mock),
+    /// so the function is no longer mocked after the guard goes out of scope, even on early return or panic.
+    /// It removes also any mocks of the function set up in thread after the guard was created.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     {
+    ///         let _guard = get_string.mock_safe_scoped(|| MockResult::Return("mocked".to_string()));
+    ///
+    ///         assert_eq!("mocked", get_string());
+    ///     }
+    ///
+    ///     assert_eq!("not mocked", get_string());
+    /// }
+    /// ```
+    fn mock_safe_scoped<M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+        &self,
+        mock: M,
+    ) -> MockGuard;
+
+    /// A variant of [mock_safe](#tymethod.mock_safe), which sets up a mock only for the duration of the body closure
+    ///
+    /// The mock replaces the one set up in thread, the body runs and then the replaced mock is put back,
+    /// even if the body panics. If there was no mock, the function is no longer mocked afterwards.
+    /// Mocks of the function set up in thread by the body are discarded as well. It's useful in nested test helpers,
+    /// which temporarily override a mock set up by their caller. Returns whatever the body returns.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+    ///
+    ///     get_string.with_mock(
+    ///         || MockResult::Return("overridden".to_string()),
+    ///         || assert_eq!("overridden", get_string()),
+    ///     );
+    ///
+    ///     assert_eq!("mocked", get_string());
+    /// }
+    /// ```
+    fn with_mock<M, B, R>(&self, mock: M, body: B) -> R
+    where
+        M: FnMut<T, Output = MockResult<T, O>> + 'static,
+        B: FnOnce() -> R;
+
+    /// A variant of [mock_safe](#tymethod.mock_safe), which sets up a mock shared by all threads
+    ///
+    /// The mock is used in every thread, where the function is not mocked in other ways, including
+    /// threads spawned by the code under test, e.g. thread pool workers. Mocks set up in a thread
+    /// with other methods take precedence over it. The mock closure must be `Send`, calls to it from
+    /// different threads are serialized. If the mocked function is called inside of the mock closure,
+    /// it runs normally.
+    ///
+    /// Unlike other mocks, global mocks are shared between tests running in parallel,
+    /// so they should be used only for functions not called by other tests.
+    /// They stay active until cleared with [clear_global_mocks](fn.clear_global_mocks.html)
+    /// or replaced with another global mock.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe_global(|| MockResult::Return("mocked".to_string()));
+    ///
+    ///     let result = std::thread::spawn(get_string).join().unwrap();
+    ///
+    ///     assert_eq!("mocked", result);
+    /// }
+    /// ```
+    fn mock_safe_global<M: FnMut<T, Output = MockResult<T, O>> + Send + 'static>(&self, mock: M);
+
+    /// A variant of [mock_safe_global](#tymethod.mock_safe_global), which mocks only a single call in any thread
+    ///
+    /// The first thread calling the function takes the mock, it's the only call handled by it.
+    /// The mock is taken with an atomic flag, so when many threads call the function at the same time,
+    /// exactly one of them gets mocked and the others run normally without waiting for the mock to finish.
+    /// Once taken the mock is removed, just like after [clear_global_mocks](fn.clear_global_mocks.html).
+    /// It's useful for deterministically injecting a single event into a group of racing threads.
+    ///
+    /// The mock closure must be `Send`, because it's moved to the thread, which takes it.
+    /// It doesn't need to be `Sync`, it's never shared and it's called at most once, so it may be `FnOnce`.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe_global_once(|| MockResult::Return("mocked".to_string()));
+    ///
+    ///     let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(get_string)).collect();
+    ///     let mut results: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    ///     results.sort();
+    ///
+    ///     assert_eq!(vec!["mocked", "not mocked", "not mocked", "not mocked"], results);
+    /// }
+    /// ```
+    fn mock_safe_global_once<M: FnOnce<T, Output = MockResult<T, O>> + Send + 'static>(
+        &self,
+        mock: M,
+    );
+
+    /// Stop mocking this function.
+    ///
+    /// All future invocations will be forwarded to the real implementation.
+    /// Does nothing if the function is not mocked.
+    /// Like setting up a mock, it has effect only in thread, where it was called.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+    ///     assert_eq!("mocked", get_string());
+    ///
+    ///     get_string.clear_mock();
+    ///
+    ///     assert_eq!("not mocked", get_string());
+    /// }
+    /// ```
+    fn clear_mock(&self);
+
+    /// Returns true if the function has a mock set up in this thread
+    ///
+    /// Mocks set up with [MockContext](struct.MockContext.html) count only while it's running.
+    /// Mocks set up with [mock_safe_global](#tymethod.mock_safe_global) are not taken into account.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     assert!(!get_string.is_mocked());
+    ///
+    ///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+    ///
+    ///     assert!(get_string.is_mocked());
+    /// }
+    /// ```
+    fn is_mocked(&self) -> bool;
+
+    /// Returns how many times the function was called since its mock was last set up
+    ///
+    /// Every call is counted, no matter if the mock returned `MockResult::Continue` or `MockResult::Return`
+    /// or if the function has any mock at all. Like mocks, the counter is kept separately for every thread.
+    /// It's reset to zero whenever a new mock is set up with [mock_raw](#tymethod.mock_raw) or
+    /// [mock_safe](#tymethod.mock_safe).
+    ///
+    /// Calls are counted only after any mock was set up in the thread or any call count was reset with
+    /// [reset_call_count](#tymethod.reset_call_count), until [clear_mocks](fn.clear_mocks.html) is called.
+    /// Otherwise mockable functions skip all bookkeeping and run as fast as possible.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+    ///
+    ///     get_string();
+    ///     get_string();
+    ///
+    ///     assert_eq!(2, get_string.call_count());
+    /// }
+    /// ```
+    fn call_count(&self) -> usize;
+
+    /// Resets the [call count](#tymethod.call_count) of the function to zero without touching its mocks
+    ///
+    /// It's useful for counting calls made in separate phases of a test with a mock set up only once
+    /// or for starting counting calls of functions in a thread, which has no mocks.
+    /// Like the counter, it affects only the thread, where it was called.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+    ///     get_string();
+    ///
+    ///     get_string.reset_call_count();
+    ///
+    ///     assert_eq!("mocked", get_string());
+    ///     assert_eq!(1, get_string.call_count());
+    /// }
+    /// ```
+    fn reset_call_count(&self);
+
+    /// Calls the function bypassing all its mocks
+    ///
+    /// The function runs normally with the given arguments, no matter what mocks are set up for it.
+    /// It's useful inside of mock closures, which wrap the original behavior, e.g. to transform its result.
+    /// Only the call made directly by this method is not mocked, any recursive calls made by the function
+    /// itself reach the mocks as usual. The call is not counted by [call_count](#tymethod.call_count)
+    /// and it doesn't panic when [mocks are required](fn.require_mocks.html).
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> String {
+    ///     "not mocked".to_string()
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_safe(|| MockResult::Return(get_string.call_real(()).to_uppercase()));
+    ///
+    ///     assert_eq!("NOT MOCKED", get_string());
+    /// }
+    /// ```
+    fn call_real(self, input: T) -> O;
+
+    #[doc(hidden)]
+    /// Called before every execution of a mockable function. Checks if mock is set and if it is, calls it.
+    /// Kept for code generated by older versions of macros, uses name of the function type in diagnostics.
+    fn call_mock(&self, input: T) -> MockResult<T, O>;
+
+    #[doc(hidden)]
+    /// Called before every execution of a mockable function. Checks if mock is set and if it is, calls it.
+    /// The name is a human readable, fully qualified name of the function used in diagnostics.
+    fn call_mock_named(&self, name: &'static str, input: T) -> MockResult<T, O>;
+
+    #[doc(hidden)]
+    /// Returns a unique ID of the function, which is used to set and get its mock.
+    unsafe fn get_mock_id(&self) -> TypeId;
+}
+
+/// Controls mocked function behavior when returned from [mock closure](trait.Mockable.html)
+pub enum MockResult<T, O> {
+    /// Function runs normally as if it was called with given arguments.
+    /// The arguments are passed inside enum variant as a tuple.
+    Continue(T),
+
+    /// Function returns immediately with a given value. The returned value is passed inside enum variant.
+    ///
+    /// If the function returns a reference, the returned value must live as long as the function's return type
+    /// requires. Mock closures passed to [mock_safe](trait.Mockable.html#tymethod.mock_safe) are limited by
+    /// the compiler to returning `'static` references or references borrowed from the arguments they receive.
+    /// Mock closures passed to [mock_raw](trait.Mockable.html#tymethod.mock_raw) can also return references to
+    /// values created in test, which must outlive all uses of the returned references, otherwise they dangle.
+    Return(O),
+
+    /// Function panics immediately with a given message.
+    ///
+    /// It's equivalent to panicking inside of the mock closure, but the panic message isn't
+    /// annotated with the name of the mocked function and the intent is clearly visible.
+    /// For diverging functions returning `!` it's the only way to avoid running them,
+    /// because no value can be returned from them.
+    Panic(String),
+}
+
+thread_local! {
+    static MOCK_STORE: MockStore = MockStore::default()
+}
+
+static GLOBAL_MOCK_STORE: LazyLock<GlobalMockStore> = LazyLock::new(GlobalMockStore::default);
+
+/// Clear all mocks in the ThreadLocal; only necessary if tests share threads
+///
+/// Removes mocks set up with [Mockable](trait.Mockable.html) and by all currently running
+/// [MockContext](struct.MockContext.html)s. Mocks in other threads are not affected.
+/// It's useful in test teardown helpers or whenever a test needs a clean slate:
+///
+/// ```
+/// #[test]
+/// fn get_string_test() {
+///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+///     get_other_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+///
+///     clear_mocks();
+///
+///     assert_eq!("not mocked", get_string());
+///     assert_eq!("not mocked", get_other_string());
+/// }
+/// ```
+pub fn clear_mocks() {
+    MOCK_STORE.with(|mock_store| mock_store.clear())
+}
+
+/// Lists names of all functions, closures and trait methods, which have mocks set up in this thread
+///
+/// Includes mocks set up with [Mockable](trait.Mockable.html), [mock_closure](fn.mock_closure.html),
+/// [mock_trait_method](fn.mock_trait_method.html) and by all currently running
+/// [MockContext](struct.MockContext.html)s. Functions are named with their full paths,
+/// closures with their keys and trait methods with their names. The list is sorted and has no duplicates.
+/// It's useful for debugging tests, which are affected by mocks left behind by other code:
+///
+/// ```
+/// #[test]
+/// fn get_string_test() {
+///     get_string.mock_safe(|| MockResult::Return("mocked".to_string()));
+///
+///     assert_eq!(vec!["my_crate::get_string"], active_mocks());
+/// }
+/// ```
+pub fn active_mocks() -> Vec<String> {
+    MOCK_STORE.with(|mock_store| mock_store.active_mock_names())
+}
+
+/// Sets up a mock for the given function
+///
+/// It's an alternative to calling [mock_safe](trait.Mockable.html#tymethod.mock_safe) with method syntax,
+/// which may be more convenient e.g. in macros.
+///
+/// ```
+/// #[test]
+/// fn get_string_test() {
+///     set_mock(get_string, || MockResult::Return("mocked".to_string()));
+///
+///     assert_eq!("mocked", get_string());
+/// }
+/// ```
+pub fn set_mock<T, O, F, M>(function: F, mock: M)
+where
+    T: Tuple,
+    F: Mockable<T, O>,
+    M: FnMut<T, Output = MockResult<T, O>> + 'static,
+{
+    function.mock_safe(mock)
+}
+
+/// Removes mocks of the given function
+///
+/// It's an alternative to calling [clear_mock](trait.Mockable.html#tymethod.clear_mock) with method syntax,
+/// which may be more convenient e.g. in macros.
+///
+/// ```
+/// #[test]
+/// fn get_string_test() {
+///     set_mock(get_string, || MockResult::Return("mocked".to_string()));
+///
+///     clear_mock(get_string);
+///
+///     assert_eq!("not mocked", get_string());
+/// }
+/// ```
+pub fn clear_mock<T: Tuple, O, F: Mockable<T, O>>(function: F) {
+    function.clear_mock()
+}
+
+/// Clear all mocks set up with [mock_safe_global](trait.Mockable.html#tymethod.mock_safe_global)
+///
+/// Affects all threads. Mocks set up in other ways are not affected.
+/// Global mocks stay usable even after a mock closure panics, so a failing test doesn't break other ones,
+/// but mocks it left behind stay active. Because tests in a binary run in parallel, tests using global mocks
+/// should be serialized, e.g. with a shared `Mutex` and clear global mocks when they start:
+///
+/// ```
+/// static GLOBAL_MOCKS_LOCK: Mutex<()> = Mutex::new(());
+///
+/// fn lock_global_mocks() -> MutexGuard<'static, ()> {
+///     // A test failing while holding the lock poisons it, the next test can safely proceed
+///     let lock = GLOBAL_MOCKS_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+///     clear_global_mocks();
+///     lock
+/// }
+/// ```
+///
+/// ```
+/// #[test]
+/// fn get_string_test() {
+///     get_string.mock_safe_global(|| MockResult::Return("mocked".to_string()));
+///
+///     clear_global_mocks();
+///
+///     assert_eq!("not mocked", get_string());
+/// }
+/// ```
+pub fn clear_global_mocks() {
+    GLOBAL_MOCK_STORE.clear()
+}
+
+/// Makes calling mockable functions without mocks panic
+///
+/// When enabled, every call to a mockable function without a mock set up panics with name of the function
+/// instead of running it normally. This allows strict tests, which fail on any unexpected interaction.
+/// Calls made from inside of the function's own mock closure still run normally.
+///
+/// The setting has effect only in thread, where it was changed, and it's not reset by
+/// [clear_mocks](fn.clear_mocks.html).
+///
+/// ```
+/// #[test]
+/// #[should_panic]
+/// fn get_string_test() {
+///     require_mocks(true);
+///
+///     get_string();
+/// }
+/// ```
+pub fn require_mocks(required: bool) {
+    MOCK_STORE.with(|mock_store| mock_store.set_mocks_required(required))
+}
+
+/// Starts measuring how long real bodies of mockable functions run in this thread
+///
+/// Every call, which runs normally because the function isn't mocked or its mock returned `Continue`,
+/// is measured from the moment the mock finishes until the function returns or panics.
+/// The durations are aggregated per function and can be read with [timing_report](fn.timing_report.html).
+/// Enabling timing discards all previously collected durations. When timing is disabled,
+/// calls only check a thread-local flag. For async functions only creation of the future is measured.
+///
+/// ```
+/// #[test]
+/// fn get_string_test() {
+///     enable_timing();
+///
+///     get_string();
+///     get_string();
+///
+///     let report = timing_report();
+///     assert_eq!("my_crate::get_string", report[0].name);
+///     assert_eq!(2, report[0].calls);
+/// }
+/// ```
+pub fn enable_timing() {
+    MOCK_STORE.with(|mock_store| {
+        mock_store.clear_timings();
+        mock_store.set_timing_enabled(true);
+    })
+}
+
+/// Stops measuring started by [enable_timing](fn.enable_timing.html), already collected durations are kept
+pub fn disable_timing() {
+    MOCK_STORE.with(|mock_store| mock_store.set_timing_enabled(false))
+}
+
+/// Returns durations of real calls collected in this thread since [enable_timing](fn.enable_timing.html)
+///
+/// There's a single entry for every measured function, the entries are sorted by the function names.
+pub fn timing_report() -> Vec<FnTiming> {
+    MOCK_STORE.with(|mock_store| mock_store.timings())
+}
+
+/// Aggregated durations of real calls of a mockable function
+///
+/// Created by [timing_report](fn.timing_report.html).
+#[derive(Clone, Debug)]
+pub struct FnTiming {
+    /// Fully qualified name of the function
+    pub name: &'static str,
+    /// Number of measured calls
+    pub calls: usize,
+    /// Sum of durations of all measured calls
+    pub total: Duration,
+    /// Duration of the shortest call
+    pub min: Duration,
+    /// Duration of the longest call
+    pub max: Duration,
+}
+
+impl FnTiming {
+    /// Average duration of a call
+    pub fn average(&self) -> Duration {
+        self.total / self.calls as u32
+    }
+}
+
+/// Starts recording names of called mockable functions in this thread
+///
+/// Every call of a mockable function is recorded, whether it's mocked or not, except for calls made with
+/// [call_real](trait.Mockable.html#tymethod.call_real). The recorded sequence can be read with
+/// [call_sequence](fn.call_sequence.html) or checked with [assert_called_in_order](fn.assert_called_in_order.html).
+/// Enabling recording discards the previously recorded sequence.
+///
+/// ```
+/// #[test]
+/// fn send_test() {
+///     record_call_sequence();
+///
+///     connect();
+///     send("message");
+///
+///     assert_called_in_order(&["my_crate::connect", "my_crate::send"]);
+/// }
+/// ```
+pub fn record_call_sequence() {
+    MOCK_STORE.with(|mock_store| {
+        mock_store.clear_call_sequence();
+        mock_store.set_recording_calls(true);
+    })
+}
+
+/// Stops recording started by [record_call_sequence](fn.record_call_sequence.html),
+/// the already recorded sequence is kept
+pub fn stop_recording_call_sequence() {
+    MOCK_STORE.with(|mock_store| mock_store.set_recording_calls(false))
+}
+
+/// Returns fully qualified names of mockable functions called in this thread in order of calls
+///
+/// The calls are recorded since [record_call_sequence](fn.record_call_sequence.html).
+/// A function appears once for every call.
+pub fn call_sequence() -> Vec<String> {
+    MOCK_STORE.with(|mock_store| mock_store.call_sequence())
+}
+
+/// Asserts that functions with the given names were called in the given order
+///
+/// The names must appear in [call_sequence](fn.call_sequence.html) in the same order, but other calls
+/// may happen before, after or between them. The names must be fully qualified, e.g. `"my_crate::module::function"`.
+pub fn assert_called_in_order(names: &[&str]) {
+    let sequence = call_sequence();
+    let mut remaining = sequence.iter();
+    let in_order = names
+        .iter()
+        .all(|name| remaining.any(|called| called == name));
+    if !in_order {
+        panic!(
+            "Mocktopus: expected calls in order {:?}, but the calls were {:?}",
+            names, sequence
+        );
+    }
+}
+
+#[doc(hidden)]
+/// Called by mockable functions, which are about to run normally.
+/// Starts measuring the call if timing is enabled, the measurement ends when the guard is dropped.
+pub fn start_timing(name: &'static str) -> Option<TimingGuard> {
+    let enabled = MOCK_STORE
+        .try_with(|mock_store| mock_store.timing_enabled())
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+    Some(TimingGuard {
+        name,
+        start: Instant::now(),
+    })
+}
+
+#[doc(hidden)]
+/// Records duration of a call when dropped
+pub struct TimingGuard {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for TimingGuard {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed();
+        // Mock store may be already destroyed if function is called during thread teardown
+        let _ = MOCK_STORE.try_with(|mock_store| mock_store.record_timing(self.name, duration));
+    }
+}
+
+impl<T: Tuple, O, F: FnOnce<T, Output = O>> Mockable<T, O> for F {
+    unsafe fn mock_raw<M: FnMut<T, Output = MockResult<T, O>>>(&self, mock: M) {
+        let boxed = Box::new(mock) as Box<dyn FnMut<_, Output = _>>;
+        let static_boxed: Box<dyn FnMut<T, Output = MockResult<T, O>> + 'static> = transmute(boxed);
+        self.mock_safe_boxed(static_boxed)
+    }
+
+    fn mock_safe<M: FnMut<T, Output = MockResult<T, O>> + 'static>(&self, mock: M) {
+        unsafe { self.mock_raw(mock) }
+    }
+
+    fn mock_safe_boxed(&self, mock: Box<dyn FnMut<T, Output = MockResult<T, O>>>) {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| unsafe {
+            mock_store.set_fn_name(id.clone(), type_name::<F>());
+            mock_store.reset_call_count(&id);
+            mock_store.add_to_thread_layer(id, mock);
+        })
+    }
+
+    fn try_mock_safe<E, M>(&self, mock: M)
+    where
+        E: Display + 'static,
+        O: 'static,
+        M: FnMut<T, Output = Result<MockResult<T, O>, E>> + 'static,
+    {
+        self.mock_safe(TryMock {
+            mock,
+            phantom_output: PhantomData,
+        })
+    }
+
+    fn mock_safe_scoped<M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+        &self,
+        mock: M,
+    ) -> MockGuard {
+        self.mock_safe(mock);
+        MockGuard {
+            id: MockId::Fn(unsafe { self.get_mock_id() }),
+            phantom_not_send: PhantomData,
+        }
+    }
+
+    fn with_mock<M, B, R>(&self, mock: M, body: B) -> R
+    where
+        M: FnMut<T, Output = MockResult<T, O>> + 'static,
+        B: FnOnce() -> R,
+    {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        let taken = MOCK_STORE.with(|mock_store| mock_store.take_from_thread_layer(&id));
+        let _restore_guard = RestoreMockGuard {
+            id,
+            taken: Some(taken),
+        };
+        self.mock_safe(mock);
+        body()
+    }
+
+    fn mock_safe_global<M: FnMut<T, Output = MockResult<T, O>> + Send + 'static>(&self, mock: M) {
+        unsafe {
+            let id = MockId::Fn(self.get_mock_id());
+            let boxed = Box::new(mock) as Box<dyn FnMut<_, Output = _> + Send>;
+            let static_boxed: Box<dyn FnMut<T, Output = MockResult<T, O>> + Send + 'static> =
+                transmute(boxed);
+            GLOBAL_MOCK_STORE.add(id, static_boxed);
+        }
+    }
+
+    fn mock_safe_global_once<M: FnOnce<T, Output = MockResult<T, O>> + Send + 'static>(
+        &self,
+        mock: M,
+    ) {
+        unsafe {
+            let id = MockId::Fn(self.get_mock_id());
+            let boxed =
+                Box::new(OnceMock { mock: Some(mock) }) as Box<dyn FnMut<_, Output = _> + Send>;
+            let static_boxed: Box<dyn FnMut<T, Output = MockResult<T, O>> + Send + 'static> =
+                transmute(boxed);
+            GLOBAL_MOCK_STORE.add_once(id, static_boxed);
+        }
+    }
+
+    fn mock_safe_times<M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+        &self,
+        count: usize,
+        mock: M,
+    ) {
+        self.mock_safe(LimitedMock {
+            remaining: count,
+            mock,
+        })
+    }
+
+    fn mock_safe_non_reentrant<M: FnMut<T, Output = MockResult<T, O>> + 'static>(&self, mock: M)
+    where
+        Self: Clone + 'static,
+        O: 'static,
+    {
+        self.mock_safe(NonReentrantMock {
+            function: self.clone(),
+            mock,
+        })
+    }
+
+    fn mock_map<G: FnMut(O) -> O + 'static>(&self, map: G)
+    where
+        Self: Clone + 'static,
+    {
+        self.mock_safe(MapMock {
+            function: self.clone(),
+            map,
+        })
+    }
+
+    fn mock_map_args<G: FnMut<T, Output = T> + 'static>(&self, map: G)
+    where
+        O: 'static,
+    {
+        self.mock_safe(MapArgsMock {
+            map,
+            phantom_output: PhantomData,
+        })
+    }
+
+    fn mock_delegate_to<G: FnMut<T, Output = O> + 'static>(&self, delegate: G) {
+        self.mock_safe(DelegateMock { delegate })
+    }
+
+    fn mock_safe_once<M: FnOnce<T, Output = MockResult<T, O>> + 'static>(&self, mock: M) {
+        self.mock_safe(OnceMock { mock: Some(mock) })
+    }
+
+    fn mock_safe_when<P, M>(&self, matcher: P, mock: M)
+    where
+        P: FnMut(&T) -> bool + 'static,
+        M: FnMut<T, Output = MockResult<T, O>> + 'static,
+    {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        let boxed = Box::new(MatchingMock { matcher, mock }) as ConditionalMock<T, O>;
+        MOCK_STORE.with(|mock_store| unsafe {
+            mock_store.set_fn_name(id.clone(), type_name::<F>());
+            mock_store.reset_call_count(&id);
+            mock_store.add_conditional_to_thread_layer(id, boxed)
+        })
+    }
+
+    fn mock_builder(&self) -> MockBuilder<T, O> {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| mock_store.set_fn_name(id.clone(), type_name::<F>()));
+        MockBuilder {
+            id,
+            branches: Vec::new(),
+        }
+    }
+
+    fn mock_returns_seq(&self, values: Vec<O>)
+    where
+        O: 'static,
+    {
+        self.mock_safe(SequenceMock {
+            values: values.into(),
+        })
+    }
+
+    fn inspect<I: FnMut(&T) + 'static>(&self, inspector: I)
+    where
+        O: 'static,
+    {
+        self.mock_safe(InspectMock {
+            inspector,
+            phantom_output: PhantomData,
+        })
+    }
+
+    fn spy(&self) -> CallRecorder<T>
+    where
+        T: Clone + 'static,
+        O: 'static,
+    {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorder = CallRecorder {
+            name: type_name::<F>(),
+            calls: Rc::downgrade(&calls),
+        };
+        self.mock_safe(SpyMock {
+            calls,
+            phantom_output: PhantomData,
+        });
+        recorder
+    }
+
+    fn clear_mock(&self) {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| mock_store.clear_id(&id))
+    }
+
+    fn is_mocked(&self) -> bool {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| mock_store.has_mock(&id))
+    }
+
+    fn call_count(&self) -> usize {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| mock_store.call_count(&id))
+    }
+
+    fn reset_call_count(&self) {
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| mock_store.reset_call_count(&id))
+    }
+
+    fn call_real(self, input: T) -> O {
+        // Untracked calls run normally anyway
+        if !MOCK_STORE.with(calls_tracked) {
+            return self.call_once(input);
+        }
+        let id = MockId::Fn(unsafe { self.get_mock_id() });
+        MOCK_STORE.with(|mock_store| mock_store.set_real_call(id.clone()));
+        let output = self.call_once(input);
+        // Not consumed if function is not mockable
+        MOCK_STORE.with(|mock_store| mock_store.take_real_call(&id));
+        output
+    }
+
+    fn call_mock(&self, input: T) -> MockResult<T, O> {
+        self.call_mock_named(type_name::<F>(), input)
+    }
+
+    fn call_mock_named(&self, name: &'static str, input: T) -> MockResult<T, O> {
+        unsafe {
+            MOCK_STORE.with(|mock_store| {
+                if !calls_tracked(mock_store) {
+                    return MockResult::Continue(input);
+                }
+                let id = MockId::Fn(self.get_mock_id());
+                if mock_store.take_real_call(&id) {
+                    return MockResult::Continue(input);
+                }
+                mock_store.record_call(name);
+                if mock_store.has_mock(&id) {
+                    return annotate_mock_panic(name, || mock_store.call(&id, input));
+                }
+                if mock_store.name_mocks_added() {
+                    let name_id = name_mock_id::<T, O>(name.to_string());
+                    if mock_store.has_mock(&name_id) {
+                        mock_store.count_call(&id);
+                        return annotate_mock_panic(name, || mock_store.call(&name_id, input));
+                    }
+                }
+                let input = if GLOBAL_MOCK_STORE.has_any_mock() {
+                    match annotate_mock_panic(name, || GLOBAL_MOCK_STORE.call(&id, input)) {
+                        MockLayerResult::Handled(result) => {
+                            mock_store.count_call(&id);
+                            return result;
+                        }
+                        MockLayerResult::Unhandled(input) => input,
+                    }
+                } else {
+                    input
+                };
+                if mock_store.all_instantiations_mocks_added() {
+                    let all_instantiations_id = MockId::AllInstantiations(Cow::Borrowed(name));
+                    if mock_store.has_mock(&all_instantiations_id) {
+                        mock_store.count_call(&id);
+                        return annotate_mock_panic(name, || {
+                            call_all_instantiations_mock(mock_store, &all_instantiations_id, input)
+                        });
+                    }
+                }
+                if mock_store.mocks_required() && !GLOBAL_MOCK_STORE.has_mock(&id) {
+                    panic!(
+                        "Mocktopus: mockable function '{}' was called without a mock, but mocks are required",
+                        name
+                    );
+                }
+                // There's no mock to run, the call is only counted
+                mock_store.count_call(&id);
+                MockResult::Continue(input)
+            })
+        }
+    }
+
+    unsafe fn get_mock_id(&self) -> TypeId {
+        (|| ()).type_id()
+    }
+}
+
+/// Calls need bookkeeping only if there are any mocks or if the thread records them
+fn calls_tracked(mock_store: &MockStore) -> bool {
+    mock_store.tracking_calls() || GLOBAL_MOCK_STORE.has_any_mock()
+}
+
+/// Runs mocks of the named function and adds the name to messages of panics coming from them.
+/// Panics are resumed without running the panic hook again, it already reported the original one.
+fn annotate_mock_panic<R>(name: &str, call_mocks: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(call_mocks)).unwrap_or_else(|payload| {
+        let payload = match payload.downcast::<RealCallPanic>() {
+            Ok(real_call_panic) => panic::resume_unwind(real_call_panic.0),
+            Err(payload) => payload,
+        };
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => Some(message.to_string()),
+            None => payload.downcast_ref::<String>().cloned(),
+        };
+        match message {
+            Some(message) => panic::resume_unwind(Box::new(format!(
+                "Mocktopus: mock of '{}' panicked: {}",
+                name, message
+            ))),
+            None => panic::resume_unwind(payload),
+        }
+    })
+}
+
+/// Payload of panic of a real function called by a mock of Mocktopus, it's not annotated as one of the mock
+struct RealCallPanic(Box<dyn Any + Send>);
+
+/// Calls the real function and marks its panics, so they keep their original payload
+fn call_real_unannotated<T: Tuple, O, F: FnOnce<T, Output = O>>(function: F, input: T) -> O {
+    panic::catch_unwind(AssertUnwindSafe(|| function.call_real(input)))
+        .unwrap_or_else(|payload| panic::resume_unwind(Box::new(RealCallPanic(payload))))
+}
+
+/// Guard removing mock of a function when dropped
+///
+/// Created by [mock_safe_scoped](trait.Mockable.html#tymethod.mock_safe_scoped).
+/// Like mocks, it's bound to the thread, where it was created.
+#[must_use = "the mock is removed as soon as the guard is dropped"]
+pub struct MockGuard {
+    id: MockId,
+    phantom_not_send: PhantomData<Rc<()>>,
+}
+
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        // Mock store may be already destroyed if guard is dropped during thread teardown
+        let _ = MOCK_STORE.try_with(|mock_store| mock_store.clear_id(&self.id));
+    }
+}
+
+/// Puts back mock taken from thread layer when dropped, even if body of the temporary mock panicked
+struct RestoreMockGuard {
+    id: MockId,
+    taken: Option<TakenMock>,
+}
+
+impl Drop for RestoreMockGuard {
+    fn drop(&mut self) {
+        if let Some(taken) = self.taken.take() {
+            // Mock store may be already destroyed if guard is dropped during thread teardown
+            let _ = MOCK_STORE
+                .try_with(|mock_store| mock_store.restore_to_thread_layer(&self.id, taken));
+        }
+    }
+}
+
+type MockBranch<T, O> = (
+    Box<dyn FnMut(&T) -> bool>,
+    Box<dyn FnMut<T, Output = MockResult<T, O>>>,
+);
+
+/// Builder of a mock with multiple branches
+///
+/// Created by [mock_builder](trait.Mockable.html#tymethod.mock_builder).
+#[must_use = "the mock is set up only when the builder is installed"]
+pub struct MockBuilder<T, O> {
+    id: MockId,
+    branches: Vec<MockBranch<T, O>>,
+}
+
+impl<T: Tuple, O> MockBuilder<T, O> {
+    /// Starts a new branch, which is used for calls with arguments matching the matcher closure
+    pub fn when<P: FnMut(&T) -> bool + 'static>(self, matcher: P) -> MockBuilderWhen<T, O> {
+        MockBuilderWhen {
+            builder: self,
+            matcher: Box::new(matcher),
+        }
+    }
+
+    /// Sets up the mock, replacing any mock of the function set up previously
+    pub fn install(self) {
+        let boxed = Box::new(BranchingMock {
+            branches: self.branches,
+        }) as Box<dyn FnMut<_, Output = _>>;
+        // Matcher and mock closures are `'static`, only arguments and output may be not
+        let static_boxed: Box<dyn FnMut<T, Output = MockResult<T, O>> + 'static> =
+            unsafe { transmute(boxed) };
+        let id = self.id;
+        MOCK_STORE.with(|mock_store| unsafe {
+            mock_store.reset_call_count(&id);
+            mock_store.add_to_thread_layer(id, static_boxed)
+        })
+    }
+}
+
+/// Branch of [MockBuilder](struct.MockBuilder.html) waiting for its mock closure
+#[must_use = "the branch is added only when its mock closure is provided"]
+pub struct MockBuilderWhen<T, O> {
+    builder: MockBuilder<T, O>,
+    matcher: Box<dyn FnMut(&T) -> bool>,
+}
+
+impl<T: Tuple, O> MockBuilderWhen<T, O> {
+    /// Finishes the branch, the mock closure is called for calls with matching arguments
+    pub fn then<M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+        mut self,
+        mock: M,
+    ) -> MockBuilder<T, O> {
+        self.builder.branches.push((self.matcher, Box::new(mock)));
+        self.builder
+    }
+}
+
+/// Shorthands for mocking functions returning `Result`
+///
+/// The trait is implemented for all functions returning `Result`, so its methods can be called on any of them.
+/// Like [Mockable](trait.Mockable.html), methods have any effect only if called on functions
+/// [annotated as mockable](https://docs.rs/mocktopus_macros).
+pub trait MockableResult<T: Tuple, V, E> {
+    /// Sets up a mock, which returns `Ok` with a clone of the given value on every call
+    ///
+    /// It's a shorthand for [mock_safe](trait.Mockable.html#tymethod.mock_safe) with a closure returning
+    /// `MockResult::Return(Ok(value.clone()))`.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> Result<String, String> {
+    ///     Err("not mocked".to_string())
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_return_ok("mocked".to_string());
+    ///
+    ///     assert_eq!(Ok("mocked".to_string()), get_string());
+    /// }
+    /// ```
+    fn mock_return_ok(&self, value: V)
+    where
+        V: Clone + 'static;
+
+    /// Sets up a mock, which returns `Err` with a clone of the given error on every call
+    ///
+    /// It's a shorthand for [mock_safe](trait.Mockable.html#tymethod.mock_safe) with a closure returning
+    /// `MockResult::Return(Err(error.clone()))`.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> Result<String, String> {
+    ///     Ok("not mocked".to_string())
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_return_err("mocked".to_string());
+    ///
+    ///     assert_eq!(Err("mocked".to_string()), get_string());
+    /// }
+    /// ```
+    fn mock_return_err(&self, error: E)
+    where
+        E: Clone + 'static;
+}
+
+impl<T: Tuple, V, E, F: Mockable<T, Result<V, E>>> MockableResult<T, V, E> for F {
+    fn mock_return_ok(&self, value: V)
+    where
+        V: Clone + 'static,
+    {
+        self.mock_safe(ReturnMock {
+            make_output: move || Ok(value.clone()),
+        })
+    }
+
+    fn mock_return_err(&self, error: E)
+    where
+        E: Clone + 'static,
+    {
+        self.mock_safe(ReturnMock {
+            make_output: move || Err(error.clone()),
+        })
+    }
+}
+
+/// Shorthands for mocking functions returning `Option`
+///
+/// The trait is implemented for all functions returning `Option`, so its methods can be called on any of them.
+/// Like [Mockable](trait.Mockable.html), methods have any effect only if called on functions
+/// [annotated as mockable](https://docs.rs/mocktopus_macros).
+pub trait MockableOption<T: Tuple, V> {
+    /// Sets up a mock, which returns `Some` with a clone of the given value on every call
+    ///
+    /// It's a shorthand for [mock_safe](trait.Mockable.html#tymethod.mock_safe) with a closure returning
+    /// `MockResult::Return(Some(value.clone()))`.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> Option<String> {
+    ///     None
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_return_some("mocked".to_string());
+    ///
+    ///     assert_eq!(Some("mocked".to_string()), get_string());
+    /// }
+    /// ```
+    fn mock_return_some(&self, value: V)
+    where
+        V: Clone + 'static;
+
+    /// Sets up a mock, which returns `None` on every call
+    ///
+    /// It's a shorthand for [mock_safe](trait.Mockable.html#tymethod.mock_safe) with a closure returning
+    /// `MockResult::Return(None)`.
+    ///
+    /// ```
+    /// #[mockable]
+    /// fn get_string() -> Option<String> {
+    ///     Some("not mocked".to_string())
+    /// }
+    ///
+    /// #[test]
+    /// fn get_string_test() {
+    ///     get_string.mock_return_none();
+    ///
+    ///     assert_eq!(None, get_string());
+    /// }
+    /// ```
+    fn mock_return_none(&self)
+    where
+        V: 'static;
+}
+
+impl<T: Tuple, V, F: Mockable<T, Option<V>>> MockableOption<T, V> for F {
+    fn mock_return_some(&self, value: V)
+    where
+        V: Clone + 'static,
+    {
+        self.mock_safe(ReturnMock {
+            make_output: move || Some(value.clone()),
+        })
+    }
+
+    fn mock_return_none(&self)
+    where
+        V: 'static,
+    {
+        self.mock_safe(ReturnMock {
+            make_output: || None,
+        })
+    }
+}
+
+/// Mocks of functions returning references borrowed from their only argument, usually `&self`
+///
+/// The trait is implemented for all functions with signatures like `fn borrow<'a>(&'a self) -> &'a Field`,
+/// so its methods can be called on any of them. Like [Mockable](trait.Mockable.html), methods have any effect
+/// only if called on functions [annotated as mockable](https://docs.rs/mocktopus_macros).
+pub trait MockableBorrow<S: ?Sized, R: ?Sized> {
+    /// A variant of [mock_safe](trait.Mockable.html#tymethod.mock_safe), which mock closure must work
+    /// for any borrow of the argument
+    ///
+    /// The returned reference is tied to the argument's borrow, so the compiler accepts only mocks returning
+    /// references into the argument or `'static` references. Unlike with `mock_safe` the mock can't keep
+    /// the argument after the call either, because its lifetime isn't resolved to a specific one.
+    ///
+    /// ```
+    /// struct Struct {
+    ///     field: Field,
+    ///     other_field: Field,
+    /// }
+    ///
+    /// #[mockable]
+    /// impl Struct {
+    ///     fn borrow(&self) -> &Field {
+    ///         &self.field
+    ///     }
+    /// }
+    ///
+    /// #[test]
+    /// fn borrow_test() {
+    ///     Struct::borrow.mock_safe_borrowing(|this| MockResult::Return(&this.other_field));
+    /// }
+    /// ```
+    ///
+    /// [mock_raw](trait.Mockable.html#tymethod.mock_raw) accepts returning references to values,
+    /// which may not outlive the call, so it's a pattern to avoid:
+    ///
+    /// ```
+    /// #[test]
+    /// fn borrow_test() {
+    ///     let field = Field::default();
+    ///     unsafe {
+    ///         Struct::borrow.mock_raw(|_| MockResult::Return(&field));
+    ///     }
+    ///     let borrowed = Struct::default().borrow();
+    ///     drop(field);
+    ///     // borrowed dangles
+    /// }
+    /// ```
+    fn mock_safe_borrowing<M>(&self, mock: M)
+    where
+        M: for<'a> FnMut(&'a S) -> MockResult<(&'a S,), &'a R> + 'static;
+}
+
+impl<S: ?Sized, R: ?Sized, F: for<'a> FnOnce(&'a S) -> &'a R> MockableBorrow<S, R> for F {
+    fn mock_safe_borrowing<M>(&self, mock: M)
+    where
+        M: for<'a> FnMut(&'a S) -> MockResult<(&'a S,), &'a R> + 'static,
+    {
+        // The mock works for any lifetime, so it can be called with any borrow of the argument
+        unsafe { Mockable::<(&S,), &R>::mock_raw(self, mock) }
+    }
+}
+
+/// Wrapper making a closure or any other callable value mockable under a user-provided key
+///
+/// Calls to the wrapper are forwarded to the wrapped closure unless a mock is set up for its key with
+/// [mock_closure](fn.mock_closure.html). It's useful for mocking callables, which are not functions,
+/// e.g. closures stored in struct fields, where the code under test has no access to the wrapped value.
+///
+/// Mocks match only wrappers with the same key and the same signature, so mocking a key with a closure of
+/// a different signature has no effect. Mock closures work exactly like ones passed to
+/// [mock_safe](trait.Mockable.html#tymethod.mock_safe).
+///
+/// ```
+/// struct Handler<F: Fn(u32) -> u32> {
+///     callback: F,
+/// }
+///
+/// #[test]
+/// fn handler_test() {
+///     let handler = Handler {
+///         callback: MockableClosure::new("double", |x: u32| x * 2),
+///     };
+///     mock_closure("double", |x: u32| MockResult::Return(x * 10));
+///
+///     assert_eq!(30, (handler.callback)(3));
+/// }
+/// ```
+pub struct MockableClosure<F> {
+    key: String,
+    closure: F,
+}
+
+impl<F> MockableClosure<F> {
+    /// Wraps a closure making it mockable under the given key
+    pub fn new(key: impl Into<String>, closure: F) -> Self {
+        MockableClosure {
+            key: key.into(),
+            closure,
+        }
+    }
+
+    fn call_key_mock<T: Tuple, O>(&self, input: T) -> MockResult<T, O> {
+        let id = closure_mock_id::<T, O>(self.key.clone());
+        MOCK_STORE.with(|mock_store| {
+            annotate_mock_panic(&self.key, || unsafe { mock_store.call(&id, input) })
+        })
+    }
+}
+
+impl<T: Tuple, F: FnOnce<T>> FnOnce<T> for MockableClosure<F> {
+    type Output = F::Output;
+
+    extern "rust-call" fn call_once(self, input: T) -> Self::Output {
+        match self.call_key_mock(input) {
+            MockResult::Continue(input) => self.closure.call_once(input),
+            MockResult::Return(output) => output,
+            MockResult::Panic(message) => panic::panic_any(message),
+        }
+    }
+}
+
+impl<T: Tuple, F: FnMut<T>> FnMut<T> for MockableClosure<F> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        match self.call_key_mock(input) {
+            MockResult::Continue(input) => self.closure.call_mut(input),
+            MockResult::Return(output) => output,
+            MockResult::Panic(message) => panic::panic_any(message),
+        }
+    }
+}
+
+impl<T: Tuple, F: Fn<T>> Fn<T> for MockableClosure<F> {
+    extern "rust-call" fn call(&self, input: T) -> Self::Output {
+        match self.call_key_mock(input) {
+            MockResult::Continue(input) => self.closure.call(input),
+            MockResult::Return(output) => output,
+            MockResult::Panic(message) => panic::panic_any(message),
+        }
+    }
+}
+
+/// Sets up a mock for all [MockableClosure](struct.MockableClosure.html)s with the given key
+///
+/// The mock has effect only in thread, where it was set, and it's removed by [clear_mocks](fn.clear_mocks.html).
+pub fn mock_closure<T: Tuple, O, M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+    key: impl Into<String>,
+    mock: M,
+) {
+    let id = closure_mock_id::<T, O>(key.into());
+    let boxed = Box::new(mock) as Box<dyn FnMut<T, Output = MockResult<T, O>>>;
+    MOCK_STORE.with(|mock_store| unsafe {
+        mock_store.reset_call_count(&id);
+        mock_store.add_to_thread_layer(id, boxed)
+    })
+}
+
+fn closure_mock_id<T, O>(key: String) -> MockId {
+    MockId::Key(key, erased_type_id::<(T, O)>())
+}
+
+/// Sets up a mock for a mockable function identified by its fully qualified name
+///
+/// The name is the same as the one in panic messages of mocked functions, e.g. `"my_crate::module::function"`,
+/// `"my_crate::module::Struct::method"` or `"my_crate::module::<Struct as Trait>::method"`.
+/// It allows stubbing functions without having their items at hand, e.g. in data-driven tests reading
+/// a list of function names. The name is resolved when the function is called, so the mock can be set up
+/// before anything refers to the function.
+///
+/// The mock is used only when the function's name matches exactly and the mock closure has the same arguments
+/// and return type as the function. Otherwise, e.g. because of a typo, the mock silently never runs,
+/// so it's worth checking with [call_count](trait.Mockable.html#tymethod.call_count) that it was used.
+/// Mocks set up for the function with [Mockable](trait.Mockable.html) take precedence over it.
+/// Otherwise the mock works exactly like one passed to [mock_safe](trait.Mockable.html#tymethod.mock_safe).
+/// It has effect only in thread, where it was set, and it's removed by [clear_mocks](fn.clear_mocks.html).
+///
+/// ```
+/// #[mockable]
+/// fn get_string(id: u32) -> String {
+///     "not mocked".to_string()
+/// }
+///
+/// #[test]
+/// fn get_string_test() {
+///     set_mock_by_name("my_crate::get_string", |_: u32| MockResult::Return("mocked".to_string()));
+///
+///     assert_eq!("mocked", get_string(1));
+/// }
+/// ```
+pub fn set_mock_by_name<T: Tuple, O, M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+    name: impl Into<String>,
+    mock: M,
+) {
+    let id = name_mock_id::<T, O>(name.into());
+    let boxed = Box::new(mock) as Box<dyn FnMut<T, Output = MockResult<T, O>>>;
+    MOCK_STORE.with(|mock_store| unsafe {
+        mock_store.reset_call_count(&id);
+        mock_store.add_to_thread_layer(id, boxed)
+    })
+}
+
+fn name_mock_id<T, O>(name: String) -> MockId {
+    MockId::Name(name, erased_type_id::<(T, O)>())
+}
+
+/// Sets up a mock for all instantiations of a generic mockable function identified by its name
+///
+/// The name is the same as the one accepted by [set_mock_by_name](fn.set_mock_by_name.html), it has no generic
+/// arguments, e.g. `"my_crate::module::function"` or `"my_crate::module::Struct::method"`, so it's shared by all
+/// instantiations. It's useful for stubbing a generic function, which is instantiated with many types,
+/// e.g. by a dependency. Because the instantiations have different signatures, the mock closure receives
+/// the arguments tuple with the type erased and returns arguments or the returned value with the type erased.
+/// They can be inspected and created with [ErasedValue](struct.ErasedValue.html) methods.
+/// If they don't have exactly the types of the called instantiation, the call panics.
+///
+/// Mocks set up for a specific instantiation, including the global ones, take precedence over it.
+/// Otherwise it works like a mock set up with [mock_safe](trait.Mockable.html#tymethod.mock_safe), it has effect
+/// only in thread, where it was set and it's removed by [clear_mocks](fn.clear_mocks.html).
+/// It's not removed by [clear_mock](trait.Mockable.html#tymethod.clear_mock), which affects only a single
+/// instantiation.
+///
+/// # Safety
+///
+/// Lifetimes are erased together with the types, so references received in arguments are
+/// accessible as `'static`. The mock closure must not let them or any values borrowing them
+/// outlive the call. Returned references must live as long as the function's return type requires.
+///
+/// ```
+/// #[mockable]
+/// fn describe<T: Debug>(value: T) -> String {
+///     format!("not mocked {:?}", value)
+/// }
+///
+/// #[test]
+/// fn describe_test() {
+///     unsafe {
+///         mock_raw_all_instantiations("my_crate::describe", |args| match args.downcast_ref::<(u32,)>() {
+///             Some(&(id,)) => MockResult::Return(ErasedValue::new(format!("mocked {}", id))),
+///             None => MockResult::Return(ErasedValue::new("mocked".to_string())),
+///         });
+///     }
+///
+///     assert_eq!("mocked 1", describe(1u32));
+///     assert_eq!("mocked", describe("text"));
+/// }
+/// ```
+pub unsafe fn mock_raw_all_instantiations<M>(name: impl Into<String>, mock: M)
+where
+    M: FnMut(ErasedValue) -> MockResult<ErasedValue, ErasedValue> + 'static,
+{
+    let id = MockId::AllInstantiations(Cow::Owned(name.into()));
+    let boxed = Box::new(AllInstantiationsMock { mock })
+        as Box<dyn FnMut<(ErasedValue,), Output = MockResult<(ErasedValue,), ErasedValue>>>;
+    MOCK_STORE.with(|mock_store| mock_store.add_to_thread_layer(id, boxed))
+}
+
+unsafe fn call_all_instantiations_mock<T: Tuple, O>(
+    mock_store: &MockStore,
+    id: &MockId,
+    input: T,
+) -> MockResult<T, O> {
+    match mock_store.call::<_, ErasedValue>(id, (ErasedValue::erase(input),)) {
+        MockResult::Continue((input,)) => MockResult::Continue(input.take_as("arguments")),
+        MockResult::Return(output) => MockResult::Return(output.take_as("returned value")),
+        MockResult::Panic(message) => MockResult::Panic(message),
+    }
+}
+
+/// Value with its type erased
+///
+/// Used by mocks set up with
+/// [mock_raw_all_instantiations](trait.Mockable.html#tymethod.mock_raw_all_instantiations)
+/// to receive arguments and return values of any instantiation of a generic function.
+/// It's similar to `Box<dyn Any>`, but it can also hold values, which aren't `'static`.
+pub struct ErasedValue {
+    type_id: TypeId,
+    type_name: &'static str,
+    value: Box<dyn Erased>,
+}
+
+trait Erased {}
+
+impl<V> Erased for V {}
+
+impl ErasedValue {
+    /// Erases the type of the value
+    pub fn new<V: 'static>(value: V) -> Self {
+        unsafe { Self::erase(value) }
+    }
+
+    /// Value's lifetimes are erased too, so it must not be used after any of them ends
+    unsafe fn erase<V>(value: V) -> Self {
+        let boxed = Box::new(value) as Box<dyn Erased + '_>;
+        ErasedValue {
+            type_id: erased_type_id::<V>(),
+            type_name: type_name::<V>(),
+            value: transmute::<Box<dyn Erased + '_>, Box<dyn Erased>>(boxed),
+        }
+    }
+
+    /// Returns the name of the value's type
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns true if the value has the given type
+    pub fn is<V: 'static>(&self) -> bool {
+        self.type_id == erased_type_id::<V>()
+    }
+
+    /// Returns a reference to the value if it has the given type
+    pub fn downcast_ref<V: 'static>(&self) -> Option<&V> {
+        match self.is::<V>() {
+            true => Some(unsafe { &*(&*self.value as *const dyn Erased as *const V) }),
+            false => None,
+        }
+    }
+
+    /// Returns the value if it has the given type or gives it back otherwise
+    pub fn downcast<V: 'static>(self) -> Result<V, Self> {
+        unsafe { self.take() }
+    }
+
+    unsafe fn take<V>(self) -> Result<V, Self> {
+        match self.type_id == erased_type_id::<V>() {
+            true => Ok(*Box::from_raw(Box::into_raw(self.value) as *mut V)),
+            false => Err(self),
+        }
+    }
+
+    unsafe fn take_as<V>(self, description: &str) -> V {
+        self.take().unwrap_or_else(|value: Self| {
+            panic!(
+                "Mocktopus: mock of all instantiations used {} of type '{}', expected '{}'",
+                description,
+                value.type_name,
+                type_name::<V>()
+            )
+        })
+    }
+}
+
+impl Debug for ErasedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("ErasedValue").field(&self.type_name).finish()
+    }
+}
+
+/// Returns a unique ID of the type ignoring its lifetimes
+fn erased_type_id<V>() -> TypeId {
+    (|| PhantomData::<V>).type_id()
+}
+
+/// Mock closure of all instantiations adapted to the shape of mock closures
+struct AllInstantiationsMock<M> {
+    mock: M,
+}
+
+impl<M: FnMut(ErasedValue) -> MockResult<ErasedValue, ErasedValue>> FnOnce<(ErasedValue,)>
+    for AllInstantiationsMock<M>
+{
+    type Output = MockResult<(ErasedValue,), ErasedValue>;
+
+    extern "rust-call" fn call_once(mut self, input: (ErasedValue,)) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<M: FnMut(ErasedValue) -> MockResult<ErasedValue, ErasedValue>> FnMut<(ErasedValue,)>
+    for AllInstantiationsMock<M>
+{
+    extern "rust-call" fn call_mut(&mut self, (input,): (ErasedValue,)) -> Self::Output {
+        match (self.mock)(input) {
+            MockResult::Continue(input) => MockResult::Continue((input,)),
+            MockResult::Return(output) => MockResult::Return(output),
+            MockResult::Panic(message) => MockResult::Panic(message),
+        }
+    }
+}
+
+/// Sets up a mock for a trait method of all implementors
+///
+/// The method is identified by path of the module containing the trait impl blocks, path of the trait
+/// as written in them and name of the method, e.g. `"my_crate::module::Trait::method"` for `impl Trait for Struct`
+/// in `my_crate::module` or `"my_crate::module::other::Trait::method"` for `impl other::Trait for Struct`.
+/// Paths starting with `::` are used as they are, e.g. `"std::fmt::Display::fmt"` for `impl ::std::fmt::Display`.
+/// Generic args of the trait are not a part of the name, instantiations of generic traits are told apart
+/// by the signature of the mock closure.
+/// The mock closure receives all arguments of the method except for the receiver, so it can be
+/// shared by all implementors no matter what type is behind `&dyn Trait` or a generic parameter.
+/// Only implementations inside of [annotated](https://docs.rs/mocktopus_macros) trait impl blocks and only methods
+/// with `self` receiver are affected.
+///
+/// Mocks set up for method of a concrete implementor with [Mockable](trait.Mockable.html) always take precedence,
+/// the trait method mock is used only for implementors, which do not have one. Otherwise the mock works exactly
+/// like one passed to [mock_safe](trait.Mockable.html#tymethod.mock_safe). It has effect only in thread,
+/// where it was set, and it's removed by [clear_mocks](fn.clear_mocks.html).
+///
+/// ```
+/// trait Greeter {
+///     fn greet(&self, name: &str) -> String;
+/// }
+///
+/// #[mockable]
+/// impl Greeter for English {
+///     fn greet(&self, name: &str) -> String {
+///         format!("Hello, {}!", name)
+///     }
+/// }
+///
+/// #[mockable]
+/// impl Greeter for Polish {
+///     fn greet(&self, name: &str) -> String {
+///         format!("Cześć, {}!", name)
+///     }
+/// }
+///
+/// #[test]
+/// fn greet_test() {
+///     mock_trait_method("my_crate::Greeter::greet", |name: &str| MockResult::Return(format!("Hi, {}!", name)));
+///
+///     let greeters: Vec<Box<dyn Greeter>> = vec![Box::new(English), Box::new(Polish)];
+///
+///     for greeter in greeters {
+///         assert_eq!("Hi, Bob!", greeter.greet("Bob"));
+///     }
+/// }
+/// ```
+pub fn mock_trait_method<T: Tuple, O, M: FnMut<T, Output = MockResult<T, O>> + 'static>(
+    trait_method: impl Into<String>,
+    mock: M,
+) {
+    let id = trait_method_mock_id::<T, O>(Cow::Owned(trait_method.into()));
+    let boxed = Box::new(mock) as Box<dyn FnMut<T, Output = MockResult<T, O>>>;
+    MOCK_STORE.with(|mock_store| unsafe {
+        mock_store.reset_call_count(&id);
+        mock_store.add_to_thread_layer(id, boxed)
+    })
+}
+
+#[doc(hidden)]
+/// Called before every execution of a mockable trait method with receiver instead of
+/// [call_mock](trait.Mockable.html#tymethod.call_mock). Calls trait method mock if it's set
+/// and the implementor's method is not mocked.
+pub fn call_trait_mock<T: Tuple, S, R: Tuple, O, F: Mockable<T, O>>(
+    function: &F,
+    name: &'static str,
+    trait_method: &'static str,
+    input: T,
+    split_receiver: impl FnOnce(T) -> (S, R),
+    join_receiver: impl FnOnce(S, R) -> T,
+) -> MockResult<T, O> {
+    let id = MockId::Fn(unsafe { function.get_mock_id() });
+    let trait_id = MOCK_STORE.with(|mock_store| {
+        if !mock_store.trait_method_mocks_added()
+            || mock_store.has_mock(&id)
+            || mock_store.has_real_call(&id)
+        {
+            return None;
+        }
+        let trait_id = trait_method_mock_id::<R, O>(Cow::Borrowed(trait_method));
+        Some(trait_id).filter(|trait_id| mock_store.has_mock(trait_id))
+    });
+    let trait_id = match trait_id {
+        Some(trait_id) if !GLOBAL_MOCK_STORE.has_mock(&id) => trait_id,
+        _ => return function.call_mock_named(name, input),
+    };
+    let (receiver, input) = split_receiver(input);
+    MOCK_STORE.with(|mock_store| {
+        mock_store.record_call(name);
+        mock_store.count_call(&id);
+        match annotate_mock_panic(name, || unsafe { mock_store.call(&trait_id, input) }) {
+            MockResult::Continue(input) => MockResult::Continue(join_receiver(receiver, input)),
+            MockResult::Return(output) => MockResult::Return(output),
+            MockResult::Panic(message) => MockResult::Panic(message),
+        }
+    })
+}
+
+fn trait_method_mock_id<T, O>(trait_method: Cow<'static, str>) -> MockId {
+    MockId::TraitMethod(trait_method, erased_type_id::<(T, O)>())
+}
+
+/// Arguments of calls recorded by a [spy](trait.Mockable.html#tymethod.spy)
+pub struct CallRecorder<T> {
+    name: &'static str,
+    calls: Weak<RefCell<Vec<T>>>,
+}
+
+impl<T: Clone> CallRecorder<T> {
+    /// Returns arguments of all recorded calls in order, in which they were made
+    ///
+    /// If the spy is no longer set up, returns no calls.
+    pub fn calls(&self) -> Vec<T> {
+        self.calls
+            .upgrade()
+            .map(|calls| calls.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// Panics if none of the recorded calls matches the given matcher
+    ///
+    /// The matcher is usually a tuple of [argument matchers](args/index.html), one for each argument.
+    /// The panic message lists all recorded calls.
+    ///
+    /// ```
+    /// #[test]
+    /// fn get_string_test() {
+    ///     let recorder = get_string.spy();
+    ///
+    ///     get_string(1, "text");
+    ///
+    ///     recorder.assert_called_with((args::eq(1), args::predicate(|text: &&str| text.starts_with("te"))));
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn assert_called_with(&self, matcher: impl Matcher<T>)
+    where
+        T: Debug,
+    {
+        let calls = self.calls();
+        if !calls.iter().any(|call| matcher.matches(call)) {
+            panic!(
+                "Mocktopus: no recorded call matches expected arguments, recorded calls: {:?}",
+                calls
+            );
+        }
+    }
+
+    /// Returns all recorded calls formatted for snapshot testing
+    ///
+    /// Each call is on a separate line with the function name followed by the `Debug` representation
+    /// of the arguments tuple. The calls are listed in order, in which they were made, so the output
+    /// is deterministic as long as the test is.
+    ///
+    /// ```
+    /// #[test]
+    /// fn get_string_test() {
+    ///     let recorder = get_string.spy();
+    ///
+    ///     get_string(1, "text");
+    ///     get_string(2, "other");
+    ///
+    ///     insta::assert_snapshot!(recorder.debug_snapshot());
+    /// }
+    /// ```
+    pub fn debug_snapshot(&self) -> String
+    where
+        T: Debug,
+    {
+        self.calls()
+            .iter()
+            .map(|call| format!("{}{:?}\n", self.name, call))
+            .collect()
+    }
+}
+
+/// Mock closure passing arguments of all calls to the inspector
+struct InspectMock<I, O> {
+    inspector: I,
+    phantom_output: PhantomData<fn() -> O>,
+}
+
+impl<T: Tuple, O, I: FnMut(&T)> FnOnce<T> for InspectMock<I, O> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, I: FnMut(&T)> FnMut<T> for InspectMock<I, O> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        (self.inspector)(&input);
+        MockResult::Continue(input)
+    }
+}
+
+/// Mock closure recording arguments of all calls
+struct SpyMock<T, O> {
+    calls: Rc<RefCell<Vec<T>>>,
+    phantom_output: PhantomData<fn() -> O>,
+}
+
+impl<T: Tuple + Clone, O> FnOnce<T> for SpyMock<T, O> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple + Clone, O> FnMut<T> for SpyMock<T, O> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        self.calls.borrow_mut().push(input.clone());
+        MockResult::Continue(input)
+    }
+}
+
+/// Mock closure running the real function with arguments of the wrapped mock if it returns `Continue`.
+/// Calls made while the real function runs are not mocked, because the mock is already running.
+struct NonReentrantMock<F, M> {
+    function: F,
+    mock: M,
+}
+
+impl<T: Tuple, O, F, M> FnOnce<T> for NonReentrantMock<F, M>
+where
+    F: FnOnce<T, Output = O> + Clone,
+    M: FnMut<T, Output = MockResult<T, O>>,
+{
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, F, M> FnMut<T> for NonReentrantMock<F, M>
+where
+    F: FnOnce<T, Output = O> + Clone,
+    M: FnMut<T, Output = MockResult<T, O>>,
+{
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        match self.mock.call_mut(input) {
+            MockResult::Continue(input) => {
+                MockResult::Return(call_real_unannotated(self.function.clone(), input))
+            }
+            MockResult::Return(output) => MockResult::Return(output),
+            MockResult::Panic(message) => MockResult::Panic(message),
+        }
+    }
+}
+
+/// Mock closure running the real function and returning its result transformed by the wrapped closure
+struct MapMock<F, G> {
+    function: F,
+    map: G,
+}
+
+impl<T: Tuple, O, F, G> FnOnce<T> for MapMock<F, G>
+where
+    F: FnOnce<T, Output = O> + Clone,
+    G: FnMut(O) -> O,
+{
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, F, G> FnMut<T> for MapMock<F, G>
+where
+    F: FnOnce<T, Output = O> + Clone,
+    G: FnMut(O) -> O,
+{
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        MockResult::Return((self.map)(call_real_unannotated(
+            self.function.clone(),
+            input,
+        )))
+    }
+}
+
+/// Mock closure running the real function with arguments transformed by the wrapped closure
+struct MapArgsMock<G, O> {
+    map: G,
+    phantom_output: PhantomData<fn() -> O>,
+}
+
+impl<T: Tuple, O, G: FnMut<T, Output = T>> FnOnce<T> for MapArgsMock<G, O> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, G: FnMut<T, Output = T>> FnMut<T> for MapArgsMock<G, O> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        MockResult::Continue(self.map.call_mut(input))
+    }
+}
+
+/// Mock closure returning results of the wrapped delegate called with all arguments
+struct DelegateMock<G> {
+    delegate: G,
+}
+
+impl<T: Tuple, O, G: FnMut<T, Output = O>> FnOnce<T> for DelegateMock<G> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, G: FnMut<T, Output = O>> FnMut<T> for DelegateMock<G> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        MockResult::Return(self.delegate.call_mut(input))
+    }
+}
+
+/// Mock closure wrapper, which panics when the wrapped mock returns an error
+struct TryMock<M, O, E> {
+    mock: M,
+    phantom_output: PhantomData<fn() -> (O, E)>,
+}
+
+impl<T: Tuple, O, E: Display, M> FnOnce<T> for TryMock<M, O, E>
+where
+    M: FnMut<T, Output = Result<MockResult<T, O>, E>>,
+{
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, E: Display, M> FnMut<T> for TryMock<M, O, E>
+where
+    M: FnMut<T, Output = Result<MockResult<T, O>, E>>,
+{
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        match self.mock.call_mut(input) {
+            Ok(result) => result,
+            Err(error) => panic!("Mocktopus: mock returned error: {}", error),
+        }
+    }
+}
+
+/// Mock closure wrapper, which forwards only a limited number of calls to the wrapped mock
+struct LimitedMock<M> {
+    remaining: usize,
+    mock: M,
+}
+
+impl<T: Tuple, O, M: FnMut<T, Output = MockResult<T, O>>> FnOnce<T> for LimitedMock<M> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, M: FnMut<T, Output = MockResult<T, O>>> FnMut<T> for LimitedMock<M> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        if self.remaining == 0 {
+            return MockResult::Continue(input);
+        }
+        self.remaining -= 1;
+        self.mock.call_mut(input)
+    }
+}
+
+/// Mock closure returning values created by the wrapped closure, no matter what the arguments are
+struct ReturnMock<G> {
+    make_output: G,
+}
+
+impl<T: Tuple, O, G: FnMut() -> O> FnOnce<T> for ReturnMock<G> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, G: FnMut() -> O> FnMut<T> for ReturnMock<G> {
+    extern "rust-call" fn call_mut(&mut self, _: T) -> Self::Output {
+        MockResult::Return((self.make_output)())
+    }
+}
+
+/// Mock closure wrapper, which forwards only the first call to the wrapped mock
+struct OnceMock<M> {
+    mock: Option<M>,
+}
+
+impl<T: Tuple, O, M: FnOnce<T, Output = MockResult<T, O>>> FnOnce<T> for OnceMock<M> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, M: FnOnce<T, Output = MockResult<T, O>>> FnMut<T> for OnceMock<M> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        match self.mock.take() {
+            Some(mock) => mock.call_once(input),
+            None => MockResult::Continue(input),
+        }
+    }
+}
+
+/// Mock closure returning queued values
+struct SequenceMock<O> {
+    values: VecDeque<O>,
+}
+
+impl<T: Tuple, O> FnOnce<T> for SequenceMock<O> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O> FnMut<T> for SequenceMock<O> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        match self.values.pop_front() {
+            Some(value) => MockResult::Return(value),
+            None => MockResult::Continue(input),
+        }
+    }
+}
+
+/// Mock closure wrapper, which forwards to the wrapped mock only calls accepted by the matcher
+struct MatchingMock<P, M> {
+    matcher: P,
+    mock: M,
+}
+
+impl<T: Tuple, O, P, M> FnOnce<T> for MatchingMock<P, M>
+where
+    P: FnMut(&T) -> bool,
+    M: FnMut<T, Output = MockResult<T, O>>,
+{
+    type Output = Result<MockResult<T, O>, T>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O, P, M> FnMut<T> for MatchingMock<P, M>
+where
+    P: FnMut(&T) -> bool,
+    M: FnMut<T, Output = MockResult<T, O>>,
+{
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        match (self.matcher)(&input) {
+            true => Ok(self.mock.call_mut(input)),
+            false => Err(input),
+        }
+    }
+}
+
+/// Mock closure, which calls the first branch with a matching matcher
+struct BranchingMock<T, O> {
+    branches: Vec<MockBranch<T, O>>,
+}
+
+impl<T: Tuple, O> FnOnce<T> for BranchingMock<T, O> {
+    type Output = MockResult<T, O>;
+
+    extern "rust-call" fn call_once(mut self, input: T) -> Self::Output {
+        self.call_mut(input)
+    }
+}
+
+impl<T: Tuple, O> FnMut<T> for BranchingMock<T, O> {
+    extern "rust-call" fn call_mut(&mut self, input: T) -> Self::Output {
+        for (matcher, mock) in self.branches.iter_mut() {
+            if matcher(&input) {
+                return mock.call_mut(input);
+            }
+        }
+        MockResult::Continue(input)
     }
 }
 
@@ -207,7 +2527,9 @@ impl<'a> MockContext<'a> {
         let mock_box = Box::new(mock) as Box<dyn FnMut<_, Output = _>>;
         let mock_box_static: Box<dyn FnMut<I, Output = MockResult<I, O>> + 'static> =
             std::mem::transmute(mock_box);
-        self.mock_layer.add(mockable.get_mock_id(), mock_box_static);
+        let id = MockId::Fn(mockable.get_mock_id());
+        MOCK_STORE.with(|mock_store| mock_store.set_fn_name(id.clone(), type_name::<F>()));
+        self.mock_layer.add(id, mock_box_static);
         self
     }
 
@@ -228,7 +2550,7 @@ impl<'a> MockContext<'a> {
 
 struct MockLayerGuard;
 
-impl<'a> Drop for MockLayerGuard {
+impl Drop for MockLayerGuard {
     fn drop(&mut self) {
         MOCK_STORE.with(|mock_store| unsafe { mock_store.remove_layer() });
     }
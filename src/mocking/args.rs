@@ -0,0 +1,92 @@
+//! Matchers of mocked functions arguments
+//!
+//! Matchers check single arguments and tuples of matchers check whole argument lists, e.g. in
+//! [CallRecorder::assert_called_with](../struct.CallRecorder.html#method.assert_called_with):
+//!
+//! ```
+//! #[test]
+//! fn get_string_test() {
+//!     let recorder = get_string.spy();
+//!
+//!     get_string(3, "text");
+//!
+//!     recorder.assert_called_with((args::eq(3), args::any()));
+//! }
+//! ```
+
+/// Checks if a value matches
+pub trait Matcher<T> {
+    /// Returns true if the value matches
+    fn matches(&self, value: &T) -> bool;
+}
+
+/// Matcher accepting values equal to the given one
+pub struct Eq<V>(V);
+
+/// Creates a matcher accepting values equal to the given one
+pub fn eq<V>(value: V) -> Eq<V> {
+    Eq(value)
+}
+
+impl<T: PartialEq<V>, V> Matcher<T> for Eq<V> {
+    fn matches(&self, value: &T) -> bool {
+        *value == self.0
+    }
+}
+
+/// Matcher accepting any value
+pub struct Any;
+
+/// Creates a matcher accepting any value
+pub fn any() -> Any {
+    Any
+}
+
+impl<T> Matcher<T> for Any {
+    fn matches(&self, _: &T) -> bool {
+        true
+    }
+}
+
+/// Matcher accepting values, for which the given closure returns true
+pub struct Predicate<F>(F);
+
+/// Creates a matcher accepting values, for which the given closure returns true
+pub fn predicate<T, F: Fn(&T) -> bool>(predicate: F) -> Predicate<F> {
+    Predicate(predicate)
+}
+
+impl<T, F: Fn(&T) -> bool> Matcher<T> for Predicate<F> {
+    fn matches(&self, value: &T) -> bool {
+        (self.0)(value)
+    }
+}
+
+macro_rules! impl_matcher_for_tuple {
+    ($($matcher:ident $value:ident $index:tt),*) => {
+        impl<$($matcher: Matcher<$value>, $value),*> Matcher<($($value,)*)> for ($($matcher,)*) {
+            #[allow(unused_variables)]
+            fn matches(&self, value: &($($value,)*)) -> bool {
+                true $(&& self.$index.matches(&value.$index))*
+            }
+        }
+    };
+}
+
+impl_matcher_for_tuple!();
+impl_matcher_for_tuple!(M0 T0 0);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5, M6 T6 6);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5, M6 T6 6, M7 T7 7);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5, M6 T6 6, M7 T7 7,
+    M8 T8 8);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5, M6 T6 6, M7 T7 7,
+    M8 T8 8, M9 T9 9);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5, M6 T6 6, M7 T7 7,
+    M8 T8 8, M9 T9 9, M10 T10 10);
+impl_matcher_for_tuple!(M0 T0 0, M1 T1 1, M2 T2 2, M3 T3 3, M4 T4 4, M5 T5 5, M6 T6 6, M7 T7 7,
+    M8 T8 8, M9 T9 9, M10 T10 10, M11 T11 11);
@@ -1,3 +1,6 @@
+use std::future::Future;
+use std::pin::Pin;
+
 /// Converts non-mutable reference to a mutable one
 ///
 /// Allows creating multiple mutable references to a single item breaking Rust's safety policy.
@@ -24,7 +27,28 @@
 ///     assert_eq!("mocked", get_string(&mut Context::default()));
 /// }
 /// ```
-#[allow(clippy::cast_ref_to_mut, clippy::mut_from_ref)]
+#[allow(invalid_reference_casting, clippy::mut_from_ref)]
 pub unsafe fn as_mut<T>(t_ref: &T) -> &mut T {
     &mut *(t_ref as *const T as *mut T)
 }
+
+/// Creates a boxed future, which immediately resolves to the given value
+///
+/// Mockable async functions return boxed futures, so this is a shorthand for returning a value from their mocks:
+///
+/// ```
+/// #[mockable]
+/// async fn get_string() -> String {
+///     "not mocked".to_string()
+/// }
+///
+/// #[tokio::test]
+/// async fn get_string_test() {
+///     get_string.mock_safe(|| MockResult::Return(ready_future("mocked".to_string())));
+///
+///     assert_eq!("mocked", get_string().await);
+/// }
+/// ```
+pub fn ready_future<'a, T: Send + 'a>(value: T) -> Pin<Box<dyn Future<Output = T> + Send + 'a>> {
+    Box::pin(std::future::ready(value))
+}
@@ -1,4 +1,5 @@
-#![feature(const_mut_refs, proc_macro_hygiene)]
+#![cfg(feature = "enabled")]
+#![feature(proc_macro_hygiene)]
 
 // Test if injecting works even if mocktopus is aliased
 extern crate mocktopus as mocktopus_aliased;
@@ -192,6 +193,35 @@ mod injector_injects_annotated_items {
         }
     }
 
+    mod injects_cfg_annotated_fns_keeping_cfg {
+        use super::*;
+
+        #[mockable]
+        mod module {
+            #[cfg(test)]
+            pub fn function() -> &'static str {
+                "not mocked"
+            }
+
+            #[cfg(not(test))]
+            pub fn function() -> &'static str {
+                "compiled out"
+            }
+        }
+
+        #[test]
+        fn when_not_mocked_then_runs_normally() {
+            assert_eq!("not mocked", module::function());
+        }
+
+        #[test]
+        fn when_mocked_then_runs_mock() {
+            unsafe { module::function.mock_raw(|| MockResult::Return("mocked")) }
+
+            assert_eq!("mocked", module::function());
+        }
+    }
+
     mod does_not_inject_mod_with_annotated_declaration {
         use super::*;
 
@@ -559,6 +589,95 @@ mod injector_does_not_inject_not_mockable_items {
             assert_eq!("not mocked", mocked_mod::mocked_submod::not_mocked_fn());
         }
     }
+
+    mod does_not_inject_not_mockable_struct_impl_fn {
+        use super::*;
+
+        struct Struct;
+
+        #[mockable]
+        impl Struct {
+            fn mocked_fn() -> &'static str {
+                "not mocked"
+            }
+
+            #[not_mockable]
+            fn not_mocked_fn() -> &'static str {
+                "not mocked"
+            }
+        }
+
+        #[test]
+        fn when_mocked_then_runs_normally() {
+            Struct::mocked_fn.mock_safe(|| MockResult::Return("mocked"));
+            Struct::not_mocked_fn.mock_safe(|| MockResult::Return("mocked"));
+
+            assert_eq!("mocked", Struct::mocked_fn());
+            assert_eq!("not mocked", Struct::not_mocked_fn());
+        }
+    }
+
+    mod does_not_inject_not_mockable_trait_impl_fn {
+        use super::*;
+
+        trait Trait {
+            fn mocked_fn() -> &'static str;
+
+            fn not_mocked_fn() -> &'static str;
+        }
+
+        struct Struct;
+
+        #[mockable]
+        impl Trait for Struct {
+            fn mocked_fn() -> &'static str {
+                "not mocked"
+            }
+
+            #[not_mockable]
+            fn not_mocked_fn() -> &'static str {
+                "not mocked"
+            }
+        }
+
+        #[test]
+        fn when_mocked_then_runs_normally() {
+            Struct::mocked_fn.mock_safe(|| MockResult::Return("mocked"));
+            Struct::not_mocked_fn.mock_safe(|| MockResult::Return("mocked"));
+
+            assert_eq!("mocked", Struct::mocked_fn());
+            assert_eq!("not mocked", Struct::not_mocked_fn());
+        }
+    }
+
+    mod does_not_inject_not_mockable_trait_default_fn {
+        use super::*;
+
+        #[mockable]
+        trait Trait {
+            fn mocked_fn() -> &'static str {
+                "not mocked"
+            }
+
+            #[not_mockable]
+            fn not_mocked_fn() -> &'static str {
+                "not mocked"
+            }
+        }
+
+        struct Struct;
+
+        impl Trait for Struct {}
+
+        #[test]
+        fn when_mocked_then_runs_normally() {
+            Struct::mocked_fn.mock_safe(|| MockResult::Return("mocked"));
+            Struct::not_mocked_fn.mock_safe(|| MockResult::Return("mocked"));
+
+            assert_eq!("mocked", Struct::mocked_fn());
+            assert_eq!("not mocked", Struct::not_mocked_fn());
+        }
+    }
 }
 
 mod injector_ignores_const_fns {
@@ -582,6 +701,41 @@ mod injector_ignores_const_fns {
 
         assert_eq!(1, const_fn());
     }
+
+    const CONST_FN_RESULT: u32 = const_fn();
+
+    #[test]
+    fn when_called_in_const_context_then_returns_1() {
+        assert_eq!(1, CONST_FN_RESULT);
+    }
+
+    struct Struct;
+
+    #[mockable]
+    impl Struct {
+        const fn const_method() -> u32 {
+            1
+        }
+
+        fn method() -> u32 {
+            Self::const_method() + 1
+        }
+    }
+
+    const CONST_METHOD_RESULT: u32 = Struct::const_method();
+
+    #[test]
+    fn when_const_method_called_in_const_context_then_returns_1() {
+        assert_eq!(1, CONST_METHOD_RESULT);
+    }
+
+    #[test]
+    fn when_other_methods_in_impl_mocked_then_they_run_mock() {
+        Struct::method.mock_safe(|| MockResult::Return(3));
+
+        assert_eq!(3, Struct::method());
+        assert_eq!(1, Struct::const_method());
+    }
 }
 
 mod injector_ignores_unsafe_fns {
@@ -600,6 +754,43 @@ mod injector_ignores_unsafe_fns {
     // Trait Mockable is not implemented for unsafe functions
 }
 
+mod injector_ignores_non_rust_abi_fns {
+    use super::*;
+
+    #[mockable]
+    extern "C" fn function(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(3, function(1, 2));
+    }
+
+    // Trait Mockable is not implemented for functions with non-Rust ABI
+}
+
+mod injector_injects_explicit_rust_abi_fns {
+    use super::*;
+
+    #[mockable]
+    extern "Rust" fn function(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(3, function(1, 2));
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock() {
+        function.mock_safe(|a, b| MockResult::Return(a - b));
+
+        assert_eq!(-1, function(1, 2));
+    }
+}
+
 mod injector_ignores_unsafe_impls {
     use super::*;
 
@@ -620,6 +811,100 @@ mod injector_ignores_unsafe_impls {
     // Trait Mockable is not implemented for unsafe functions
 }
 
+mod injector_injects_fns_with_pattern_args {
+    use super::*;
+
+    struct Pair {
+        left: u32,
+        right: u32,
+    }
+
+    #[mockable]
+    fn tuple_pattern((x, _): (u32, u32)) -> u32 {
+        x
+    }
+
+    #[mockable]
+    fn ref_pattern(&x: &u32) -> u32 {
+        x
+    }
+
+    #[mockable]
+    fn ref_binding(ref x: u32) -> u32 {
+        *x
+    }
+
+    #[mockable]
+    fn struct_pattern(Pair { left, right }: Pair, mut offset: u32) -> u32 {
+        offset += 1;
+        left + right + offset
+    }
+
+    #[mockable]
+    fn mixed_pattern((mut a, b): (u32, u32), _: u32, [c, d]: [u32; 2]) -> u32 {
+        a *= 2;
+        a + b + c + d
+    }
+
+    struct Struct;
+
+    #[mockable]
+    impl Struct {
+        fn tuple_pattern(&self, (x, y): (u32, u32)) -> u32 {
+            x + y
+        }
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(1, tuple_pattern((1, 2)));
+        assert_eq!(3, ref_pattern(&3));
+        assert_eq!(4, ref_binding(4));
+        assert_eq!(6, struct_pattern(Pair { left: 1, right: 2 }, 2));
+        assert_eq!(9, mixed_pattern((1, 2), 0, [2, 3]));
+        assert_eq!(3, Struct.tuple_pattern((1, 2)));
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock() {
+        tuple_pattern.mock_safe(|(x, y)| MockResult::Return(x + y + 10));
+        ref_pattern.mock_safe(|x| MockResult::Return(*x + 10));
+        ref_binding.mock_safe(|x| MockResult::Return(x + 10));
+        struct_pattern.mock_safe(|pair, offset| MockResult::Return(pair.left + offset));
+        mixed_pattern.mock_safe(|_, ignored, _| MockResult::Return(ignored));
+        Struct::tuple_pattern.mock_safe(|_, (x, _)| MockResult::Return(x));
+
+        assert_eq!(13, tuple_pattern((1, 2)));
+        assert_eq!(13, ref_pattern(&3));
+        assert_eq!(14, ref_binding(4));
+        assert_eq!(3, struct_pattern(Pair { left: 1, right: 2 }, 2));
+        assert_eq!(5, mixed_pattern((1, 2), 5, [2, 3]));
+        assert_eq!(1, Struct.tuple_pattern((1, 2)));
+    }
+
+    #[test]
+    fn when_continue_mocked_then_destructures_modified_args() {
+        tuple_pattern.mock_safe(|(x, y)| MockResult::Continue(((y, x),)));
+        ref_pattern.mock_safe(|_| MockResult::Continue((&5,)));
+        struct_pattern.mock_safe(|pair, offset| {
+            MockResult::Continue((
+                Pair {
+                    left: pair.right,
+                    right: 0,
+                },
+                offset,
+            ))
+        });
+        mixed_pattern
+            .mock_safe(|(a, b), ignored, [c, d]| MockResult::Continue(((b, a), ignored, [d, c])));
+
+        assert_eq!(2, tuple_pattern((1, 2)));
+        assert_eq!(5, ref_pattern(&3));
+        assert_eq!(5, struct_pattern(Pair { left: 1, right: 2 }, 2));
+        assert_eq!(10, mixed_pattern((1, 2), 0, [2, 3]));
+    }
+}
+
 mod injector_does_not_inject_macro_generated_fns {
     use super::*;
 
@@ -901,6 +1186,7 @@ mod injecting_lifetimed_fn_of_same_lifetimed_trait_impl_of_same_lifetimed_struct
         fn function(arg: &'a str) -> &'a str;
     }
 
+    #[allow(dead_code)]
     struct Struct<'a>(&'a str);
 
     #[mockable]
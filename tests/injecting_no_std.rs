@@ -1,3 +1,4 @@
+#![cfg(feature = "enabled")]
 #![no_std]
 
 extern crate mocktopus;
@@ -1,3 +1,10 @@
+#![cfg(feature = "enabled")]
+#![allow(
+    clippy::extra_unused_type_parameters,
+    clippy::multiple_bound_locations,
+    clippy::needless_borrows_for_generic_args
+)]
+
 extern crate mocktopus;
 
 mod mocking_fns;
@@ -141,6 +148,87 @@ mod mocking_generic_over_a_reference_does_not_mock_opposite_mutability_variant {
     }
 }
 
+mod mocking_generic_instantiation_mocks_it_when_called_from_other_modules {
+    use super::*;
+    use std::fmt::Display;
+
+    #[mockable]
+    fn function<T: Display>(generic: T) -> String {
+        format!("not mocked {}", generic)
+    }
+
+    mod module_1 {
+        pub fn call(generic: u32) -> String {
+            super::function(generic)
+        }
+    }
+
+    mod module_2 {
+        use super::*;
+
+        pub fn call<T: Display>(generic: T) -> String {
+            super::function(generic)
+        }
+
+        pub fn mock() {
+            super::function::<u32>.mock_safe(|_| MockResult::Return("mocked".to_string()));
+        }
+    }
+
+    #[test]
+    fn when_mocked_then_all_modules_run_mock() {
+        function::<u32>.mock_safe(|generic| MockResult::Return(format!("mocked {}", generic)));
+
+        assert_eq!("mocked 1", function(1u32));
+        assert_eq!("mocked 2", module_1::call(2));
+        assert_eq!("mocked 3", module_2::call(3u32));
+        assert_eq!("not mocked 4", module_2::call(4u64));
+    }
+
+    #[test]
+    fn when_mocked_from_other_module_then_runs_mock() {
+        module_2::mock();
+
+        assert_eq!("mocked", function(1u32));
+        assert_eq!("mocked", module_1::call(2));
+    }
+}
+
+mod mocking_generic_struct_constructor_mocks_each_type_parameter_separately {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Container<T>(T);
+
+    #[mockable]
+    impl<T: Default> Container<T> {
+        fn make() -> Self {
+            Container(T::default())
+        }
+    }
+
+    #[test]
+    fn when_mocked_for_two_types_then_each_runs_its_own_mock() {
+        Container::<String>::make.mock_safe(|| MockResult::Return(Container("mocked".to_string())));
+        Container::<u32>::make.mock_safe(|| MockResult::Return(Container(1)));
+
+        assert_eq!(Container("mocked".to_string()), Container::<String>::make());
+        assert_eq!(Container(1), Container::<u32>::make());
+        assert_eq!(Container(false), Container::<bool>::make());
+    }
+
+    #[test]
+    fn when_mock_cleared_for_one_type_then_other_is_still_mocked() {
+        Container::<String>::make.mock_safe(|| MockResult::Return(Container("mocked".to_string())));
+        Container::<u32>::make.mock_safe(|| MockResult::Return(Container(1)));
+
+        Container::<String>::make.clear_mock();
+
+        assert_eq!(Container(String::new()), Container::<String>::make());
+        assert_eq!(Container(1), Container::<u32>::make());
+    }
+}
+
 mod mocking_trait_default_for_struct_does_not_mock_same_default_for_another_struct {
     use super::*;
 
@@ -339,6 +427,15 @@ mod clear_mocks {
         assert_eq!("not mocked 2", mockable_2());
     }
 
+    #[test]
+    fn clearing_mocks_in_other_thread_does_not_clear_mocks() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+
+        std::thread::spawn(clear_mocks).join().unwrap();
+
+        assert_eq!("mocked 1", mockable_1());
+    }
+
     #[test]
     fn clearing_mocks_inside_context_clears_all_mocks() {
         assert_eq!("not mocked 1", mockable_1());
@@ -410,6 +507,17 @@ mod clear_mock {
         assert_eq!("mocked 2", mockable_2());
     }
 
+    #[test]
+    fn clearing_in_other_thread_does_not_clear_mock() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+
+        std::thread::spawn(|| mockable_1.clear_mock())
+            .join()
+            .unwrap();
+
+        assert_eq!("mocked 1", mockable_1());
+    }
+
     #[test]
     fn clearing_mocks_inside_context_clears_mocks_in_all_contexts() {
         assert_eq!("not mocked 1", mockable_1());
@@ -426,7 +534,7 @@ mod clear_mock {
     }
 }
 
-mod mock_context {
+mod active_mocks {
     use super::*;
 
     #[mockable]
@@ -439,85 +547,3263 @@ mod mock_context {
         "not mocked 2"
     }
 
-    #[mockable]
-    fn mockable_string() -> String {
-        "not mocked".to_string()
+    #[test]
+    fn when_no_mocks_then_empty() {
+        assert!(active_mocks().is_empty());
     }
 
     #[test]
-    fn context_mocks_mock_only_inside_run_closure() {
-        assert_eq!("not mocked 1", mockable_1());
-        assert_eq!("not mocked 2", mockable_2());
+    fn when_functions_mocked_then_lists_their_paths_sorted() {
+        mockable_2.mock_safe(|| MockResult::Return("mocked 2"));
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+
+        assert_eq!(
+            vec![
+                concat!(module_path!(), "::mockable_1"),
+                concat!(module_path!(), "::mockable_2"),
+            ],
+            active_mocks()
+        );
+    }
+
+    #[test]
+    fn when_function_mocked_many_times_then_listed_once() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
         MockContext::new()
-            .mock_safe(mockable_1, || MockResult::Return("mocked 1"))
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1 context"))
             .run(|| {
-                assert_eq!("mocked 1", mockable_1());
-                assert_eq!("not mocked 2", mockable_2());
+                assert_eq!(
+                    vec![concat!(module_path!(), "::mockable_1")],
+                    active_mocks()
+                );
             });
-        assert_eq!("not mocked 1", mockable_1());
-        assert_eq!("not mocked 2", mockable_2());
     }
 
     #[test]
-    fn context_mocks_with_no_mocks_have_no_effect() {
-        assert_eq!("not mocked 1", mockable_1());
-        MockContext::new().run(|| {
-            assert_eq!("not mocked 1", mockable_1());
+    fn when_closure_and_trait_method_mocked_then_lists_their_keys() {
+        mock_closure("closure_key", |x: u32| MockResult::Return(x));
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), || {
+            MockResult::Return(())
         });
-        assert_eq!("not mocked 1", mockable_1());
+
+        assert_eq!(
+            vec!["closure_key", concat!(module_path!(), "::Trait::method")],
+            active_mocks()
+        );
     }
 
     #[test]
-    fn context_mocks_with_multiple_mocks_of_the_same_function_use_only_last_mock() {
-        assert_eq!("not mocked 1", mockable_1());
-        MockContext::new()
-            .mock_safe(mockable_1, || MockResult::Return("mocked 1 A"))
-            .mock_safe(mockable_1, || MockResult::Return("mocked 1 B"))
-            .mock_safe(mockable_1, || MockResult::Return("mocked 1 C"))
-            .run(|| {
-                assert_eq!("mocked 1 C", mockable_1());
-            });
-        assert_eq!("not mocked 1", mockable_1());
+    fn when_mocks_cleared_then_not_listed() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+        mockable_2.mock_safe_when(|_| true, || MockResult::Return("mocked 2"));
+
+        mockable_1.clear_mock();
+
+        assert_eq!(
+            vec![concat!(module_path!(), "::mockable_2")],
+            active_mocks()
+        );
+
+        clear_mocks();
+
+        assert!(active_mocks().is_empty());
+    }
+}
+
+mod compat_mockall {
+    use super::*;
+    use mocktopus::compat::mockall::*;
+    use mocktopus::mocking::args;
+
+    #[mockable]
+    fn mockable(x: u32) -> String {
+        format!("not mocked {}", x)
     }
 
     #[test]
-    fn nested_context_mocks_shadow_outside_mocks_only_inside_run_closure() {
-        assert_eq!("not mocked 1", mockable_1());
-        assert_eq!("not mocked 2", mockable_2());
-        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
-        assert_eq!("mocked 1", mockable_1());
-        assert_eq!("not mocked 2", mockable_2());
-        MockContext::new()
-            .mock_safe(mockable_1, || MockResult::Return("mocked 1 context 1"))
-            .mock_safe(mockable_2, || MockResult::Return("mocked 2 context 1"))
-            .run(|| {
-                assert_eq!("mocked 1 context 1", mockable_1());
-                assert_eq!("mocked 2 context 1", mockable_2());
-                MockContext::new()
-                    .mock_safe(mockable_1, || MockResult::Return("mocked 1 context 2"))
-                    .run(|| {
-                        assert_eq!("mocked 1 context 2", mockable_1());
-                        assert_eq!("mocked 2 context 1", mockable_2());
-                    });
-                assert_eq!("mocked 1 context 1", mockable_1());
-                assert_eq!("mocked 2 context 1", mockable_2());
-            });
-        assert_eq!("mocked 1", mockable_1());
-        assert_eq!("not mocked 2", mockable_2());
+    fn when_returning_then_returns_values_created_from_args() {
+        let _expectation = expect(mockable).returning(|x| format!("mocked {}", x));
+
+        assert_eq!("mocked 1", mockable(1));
+        assert_eq!("mocked 2", mockable(2));
     }
 
     #[test]
-    fn calling_function_with_shadowed_mock_from_inside_mock_closure_calls_shadowed_mock() {
-        assert_eq!("not mocked", mockable_string());
-        mockable_string.mock_safe(|| MockResult::Return(format!("{}, mocked", mockable_string())));
-        assert_eq!("not mocked, mocked", mockable_string());
-        MockContext::new()
-            .mock_safe(mockable_string, || {
-                MockResult::Return(format!("{}, mocked context", mockable_string()))
-            })
-            .run(|| {
-                assert_eq!("not mocked, mocked, mocked context", mockable_string());
+    fn when_return_const_then_returns_clones_of_value() {
+        let _expectation = expect(mockable).times(2).return_const("mocked".to_string());
+
+        assert_eq!("mocked", mockable(1));
+        assert_eq!("mocked", mockable(2));
+    }
+
+    #[test]
+    fn when_guard_dropped_then_mock_is_removed() {
+        {
+            let _expectation = expect(mockable).once().return_const("mocked".to_string());
+
+            assert_eq!("mocked", mockable(1));
+        }
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "to be called exactly 2 times, but it was called 1 times")]
+    fn when_called_too_few_times_then_dropping_guard_panics() {
+        let _expectation = expect(mockable).times(2).return_const("mocked".to_string());
+
+        mockable(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "called more than 1 times")]
+    fn when_called_too_many_times_then_panics() {
+        let _expectation = expect(mockable).once().return_const("mocked".to_string());
+
+        mockable(1);
+        mockable(2);
+    }
+
+    #[test]
+    fn when_never_and_not_called_then_passes() {
+        let _expectation = expect(mockable).never().return_const("mocked".to_string());
+    }
+
+    #[test]
+    fn when_with_matching_args_then_returns() {
+        let _expectation = expect(mockable)
+            .with((args::eq(1),))
+            .once()
+            .returning(|x| format!("mocked {}", x));
+
+        assert_eq!("mocked 1", mockable(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "called with unexpected arguments")]
+    fn when_with_not_matching_args_then_panics() {
+        let _expectation = expect(mockable)
+            .with((args::eq(1),))
+            .returning(|x| format!("mocked {}", x));
+
+        mockable(2);
+    }
+}
+
+mod set_mock_by_name {
+    use super::*;
+
+    #[mockable]
+    fn mockable(x: u32) -> String {
+        format!("not mocked {}", x)
+    }
+
+    #[mockable]
+    fn generic<T>(_: T) -> &'static str {
+        "not mocked"
+    }
+
+    struct Struct;
+
+    #[mockable]
+    impl Struct {
+        fn method(&self) -> &'static str {
+            "not mocked"
+        }
+    }
+
+    #[test]
+    fn when_name_matches_then_runs_mock() {
+        set_mock_by_name(concat!(module_path!(), "::mockable"), |x: u32| {
+            MockResult::Return(format!("mocked {}", x))
+        });
+
+        assert_eq!("mocked 1", mockable(1));
+        assert_eq!(1, mockable.call_count());
+    }
+
+    #[test]
+    fn when_method_name_matches_then_runs_mock() {
+        set_mock_by_name(concat!(module_path!(), "::Struct::method"), |_: &Struct| {
+            MockResult::Return("mocked")
+        });
+
+        assert_eq!("mocked", Struct.method());
+    }
+
+    #[test]
+    fn when_name_does_not_match_then_runs_normally() {
+        set_mock_by_name(concat!(module_path!(), "::mockabel"), |x: u32| {
+            MockResult::Return(format!("mocked {}", x))
+        });
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+
+    #[test]
+    fn when_signature_does_not_match_then_runs_normally() {
+        set_mock_by_name(concat!(module_path!(), "::mockable"), |x: u64| {
+            MockResult::Return(format!("mocked {}", x))
+        });
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+
+    #[test]
+    fn when_signature_has_same_type_name_but_does_not_match_then_runs_normally() {
+        let call = {
+            struct Value;
+            || generic(Value)
+        };
+        {
+            // Has the same type name as the other `Value`
+            struct Value;
+            set_mock_by_name(concat!(module_path!(), "::generic"), |_: Value| {
+                MockResult::Return("mocked")
             });
-        assert_eq!("not mocked, mocked", mockable_string());
+        }
+
+        assert_eq!("not mocked", call());
+    }
+
+    #[test]
+    fn when_mocked_with_mockable_then_mockable_mock_takes_precedence() {
+        set_mock_by_name(concat!(module_path!(), "::mockable"), |x: u32| {
+            MockResult::Return(format!("mocked by name {}", x))
+        });
+        mockable.mock_safe(|x| MockResult::Return(format!("mocked {}", x)));
+
+        assert_eq!("mocked 1", mockable(1));
+
+        mockable.clear_mock();
+
+        assert_eq!("mocked by name 1", mockable(1));
+    }
+
+    #[test]
+    fn when_mocks_cleared_then_runs_normally() {
+        set_mock_by_name(concat!(module_path!(), "::mockable"), |x: u32| {
+            MockResult::Return(format!("mocked {}", x))
+        });
+
+        clear_mocks();
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+
+    #[test]
+    fn when_mocks_cleared_and_mock_set_again_then_runs_mock() {
+        set_mock_by_name(concat!(module_path!(), "::mockable"), |x: u32| {
+            MockResult::Return(format!("mocked {}", x))
+        });
+        clear_mocks();
+        set_mock_by_name(concat!(module_path!(), "::mockable"), |x: u32| {
+            MockResult::Return(format!("mocked again {}", x))
+        });
+
+        assert_eq!("mocked again 1", mockable(1));
+    }
+}
+
+mod timing {
+    use super::*;
+    use std::time::Duration;
+
+    #[mockable]
+    fn sleeping(millis: u64) -> u64 {
+        std::thread::sleep(Duration::from_millis(millis));
+        millis
+    }
+
+    #[mockable]
+    fn other() -> u32 {
+        1
+    }
+
+    #[test]
+    fn when_timing_not_enabled_then_report_is_empty() {
+        sleeping(1);
+
+        assert!(timing_report().is_empty());
+    }
+
+    #[test]
+    fn when_timing_enabled_then_reports_real_calls_per_function() {
+        enable_timing();
+
+        sleeping(5);
+        sleeping(10);
+        other();
+
+        let report = timing_report();
+        assert_eq!(2, report.len());
+        assert_eq!(concat!(module_path!(), "::other"), report[0].name);
+        assert_eq!(1, report[0].calls);
+        assert_eq!(concat!(module_path!(), "::sleeping"), report[1].name);
+        assert_eq!(2, report[1].calls);
+        assert!(report[1].min >= Duration::from_millis(5));
+        assert!(report[1].max >= Duration::from_millis(10));
+        assert!(report[1].total >= Duration::from_millis(15));
+        assert_eq!(report[1].total / 2, report[1].average());
+    }
+
+    #[test]
+    fn when_mock_continues_then_measures_real_call_with_modified_args() {
+        sleeping.mock_safe(|millis| MockResult::Continue((millis + 10,)));
+        enable_timing();
+
+        sleeping(0);
+
+        assert!(timing_report()[0].total >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn when_mock_returns_then_does_not_measure() {
+        sleeping.mock_safe(|_| MockResult::Return(0));
+        enable_timing();
+
+        sleeping(1);
+
+        assert!(timing_report().is_empty());
+    }
+
+    #[test]
+    fn when_timing_disabled_then_keeps_collected_durations() {
+        enable_timing();
+        other();
+
+        disable_timing();
+        other();
+
+        assert_eq!(1, timing_report()[0].calls);
+    }
+
+    #[test]
+    fn when_timing_enabled_again_then_discards_collected_durations() {
+        enable_timing();
+        other();
+
+        enable_timing();
+
+        assert!(timing_report().is_empty());
+    }
+}
+
+mod call_sequence {
+    use super::*;
+
+    #[mockable]
+    fn connect() {}
+
+    #[mockable]
+    fn send(_message: &str) {}
+
+    #[mockable]
+    fn disconnect() {}
+
+    fn names(functions: &[&str]) -> Vec<String> {
+        functions
+            .iter()
+            .map(|function| format!("{}::{}", module_path!(), function))
+            .collect()
+    }
+
+    #[test]
+    fn when_not_recording_then_sequence_is_empty() {
+        connect();
+
+        assert!(call_sequence().is_empty());
+    }
+
+    #[test]
+    fn when_recording_then_sequence_has_all_calls_in_order() {
+        send.mock_safe(|_| MockResult::Return(()));
+        record_call_sequence();
+
+        connect();
+        send("a");
+        send("b");
+        disconnect();
+
+        assert_eq!(
+            names(&["connect", "send", "send", "disconnect"]),
+            call_sequence()
+        );
+    }
+
+    #[test]
+    fn when_recording_stopped_then_keeps_recorded_calls() {
+        record_call_sequence();
+        connect();
+
+        stop_recording_call_sequence();
+        send("a");
+
+        assert_eq!(names(&["connect"]), call_sequence());
+    }
+
+    #[test]
+    fn when_recording_started_again_then_discards_recorded_calls() {
+        record_call_sequence();
+        connect();
+
+        record_call_sequence();
+        send("a");
+
+        assert_eq!(names(&["send"]), call_sequence());
+    }
+
+    #[test]
+    fn when_called_in_order_then_assertion_passes() {
+        record_call_sequence();
+
+        connect();
+        send("a");
+        disconnect();
+
+        let names = names(&["connect", "disconnect"]);
+        assert_called_in_order(&[&names[0], &names[1]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected calls in order")]
+    fn when_called_out_of_order_then_assertion_panics() {
+        record_call_sequence();
+
+        send("a");
+        connect();
+
+        let names = names(&["connect", "send"]);
+        assert_called_in_order(&[&names[0], &names[1]]);
+    }
+}
+
+mod mock_raw_all_instantiations {
+    use super::*;
+    use std::fmt::Debug;
+
+    #[mockable]
+    fn describe<T: Debug>(value: T) -> String {
+        format!("not mocked {:?}", value)
+    }
+
+    struct Wrapper<T>(T);
+
+    #[mockable]
+    impl<T: Debug> Wrapper<T> {
+        fn describe<U: Debug>(&self, value: U) -> String {
+            format!("not mocked {:?} {:?}", self.0, value)
+        }
+    }
+
+    #[test]
+    fn when_mocked_then_all_instantiations_return_mocking_result() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |_| {
+                MockResult::Return(ErasedValue::new("mocked".to_string()))
+            });
+        }
+
+        assert_eq!("mocked", describe(1u32));
+        assert_eq!("mocked", describe("text"));
+        assert_eq!("mocked", describe(vec![1u8]));
+    }
+
+    #[test]
+    fn when_mocked_then_mock_receives_erased_args() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |args| {
+                match args.downcast::<(u32,)>() {
+                    Ok((number,)) => {
+                        MockResult::Return(ErasedValue::new(format!("mocked {}", number)))
+                    }
+                    Err(args) => MockResult::Continue(args),
+                }
+            });
+        }
+
+        assert_eq!("mocked 1", describe(1u32));
+        assert_eq!("not mocked 2", describe(2u64));
+    }
+
+    #[test]
+    fn when_mocked_with_args_borrowing_locals_then_mock_receives_them() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |args| {
+                let text = args.downcast_ref::<(&str,)>().map(|(text,)| text.len());
+                MockResult::Return(ErasedValue::new(format!("mocked {:?}", text)))
+            });
+        }
+        let text = "text".to_string();
+
+        assert_eq!("mocked Some(4)", describe(text.as_str()));
+        assert_eq!("mocked None", describe(1u32));
+    }
+
+    #[test]
+    fn when_continue_mocked_then_runs_with_modified_args() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |args| {
+                match args.downcast::<(u32,)>() {
+                    Ok((number,)) => MockResult::Continue(ErasedValue::new((number + 1,))),
+                    Err(args) => MockResult::Continue(args),
+                }
+            });
+        }
+
+        assert_eq!("not mocked 2", describe(1u32));
+        assert_eq!("not mocked \"text\"", describe("text"));
+    }
+
+    #[test]
+    fn when_mocked_then_mocks_methods_of_all_instantiations_of_struct() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::Wrapper::describe"), |_| {
+                MockResult::Return(ErasedValue::new("mocked".to_string()))
+            });
+        }
+
+        assert_eq!("mocked", Wrapper(1u8).describe(2u8));
+        assert_eq!("mocked", Wrapper("a").describe(2u64));
+    }
+
+    #[test]
+    fn when_instantiation_mocked_then_its_mock_takes_precedence() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |_| {
+                MockResult::Return(ErasedValue::new("mocked all".to_string()))
+            });
+        }
+        describe::<u32>.mock_safe(|_| MockResult::Return("mocked u32".to_string()));
+
+        assert_eq!("mocked u32", describe(1u32));
+        assert_eq!("mocked all", describe(1u64));
+    }
+
+    #[test]
+    fn when_mocked_then_call_count_is_counted_per_instantiation() {
+        unsafe {
+            mock_raw_all_instantiations(
+                concat!(module_path!(), "::describe"),
+                MockResult::Continue,
+            );
+        }
+
+        describe(1u32);
+        describe(2u32);
+        describe(3u64);
+
+        assert_eq!(2, describe::<u32>.call_count());
+        assert_eq!(1, describe::<u64>.call_count());
+    }
+
+    #[test]
+    fn when_mocks_cleared_then_runs_normally() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |_| {
+                MockResult::Return(ErasedValue::new("mocked".to_string()))
+            });
+        }
+
+        clear_mocks();
+
+        assert_eq!("not mocked 1", describe(1u32));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "mock of all instantiations used returned value of type '&str', expected 'alloc::string::String'"
+    )]
+    fn when_mock_returns_wrong_type_then_panics() {
+        unsafe {
+            mock_raw_all_instantiations(concat!(module_path!(), "::describe"), |_| {
+                MockResult::Return(ErasedValue::new("mocked"))
+            });
+        }
+
+        describe(1u32);
+    }
+
+    #[test]
+    fn erased_value_reports_its_type() {
+        let value = ErasedValue::new(1u32);
+
+        assert!(value.is::<u32>());
+        assert!(!value.is::<u64>());
+        assert_eq!("u32", value.type_name());
+        assert_eq!(None, value.downcast_ref::<u64>());
+        assert_eq!(Some(&1), value.downcast_ref::<u32>());
+        assert_eq!(1u32, value.downcast::<u32>().unwrap());
+    }
+}
+
+mod mock_safe_borrowing {
+    use super::*;
+
+    struct Struct {
+        field: String,
+        other_field: String,
+    }
+
+    #[mockable]
+    impl Struct {
+        fn borrow(&self) -> &String {
+            &self.field
+        }
+    }
+
+    #[mockable]
+    fn first(slice: &[u32]) -> &u32 {
+        &slice[0]
+    }
+
+    fn new_struct() -> Struct {
+        Struct {
+            field: "field".to_string(),
+            other_field: "other field".to_string(),
+        }
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!("field", new_struct().borrow());
+    }
+
+    #[test]
+    fn when_mocked_then_returns_reference_into_receiver() {
+        Struct::borrow.mock_safe_borrowing(|this| MockResult::Return(&this.other_field));
+        let instance = new_struct();
+
+        assert_eq!("other field", instance.borrow());
+    }
+
+    #[test]
+    fn when_mocked_with_unsized_arg_then_returns_reference_into_arg() {
+        first.mock_safe_borrowing(|slice| MockResult::Return(&slice[slice.len() - 1]));
+
+        assert_eq!(&3, first(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn when_continue_mocked_then_runs_with_modified_args() {
+        static OTHER: [u32; 1] = [4];
+        first.mock_safe_borrowing(|_| MockResult::Continue((&OTHER[..],)));
+
+        assert_eq!(&4, first(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn when_mocked_then_call_is_counted() {
+        Struct::borrow.mock_safe_borrowing(|this| MockResult::Continue((this,)));
+
+        new_struct().borrow();
+
+        assert_eq!(1, Struct::borrow.call_count());
+    }
+}
+
+mod with_mock {
+    use super::*;
+
+    #[mockable]
+    fn mockable(x: u32) -> String {
+        format!("not mocked {}", x)
+    }
+
+    fn mock_returning(text: &'static str) -> impl FnMut(u32) -> MockResult<(u32,), String> {
+        move |_| MockResult::Return(text.to_string())
+    }
+
+    #[test]
+    fn when_nested_then_restores_mocks_in_reverse_order() {
+        mockable.mock_safe(mock_returning("mocked 1"));
+
+        mockable.with_mock(mock_returning("mocked 2"), || {
+            assert_eq!("mocked 2", mockable(0));
+            mockable.with_mock(mock_returning("mocked 3"), || {
+                assert_eq!("mocked 3", mockable(0));
+            });
+            assert_eq!("mocked 2", mockable(0));
+        });
+
+        assert_eq!("mocked 1", mockable(0));
+    }
+
+    #[test]
+    fn when_not_mocked_before_then_is_not_mocked_after() {
+        mockable.with_mock(mock_returning("mocked"), || {
+            assert_eq!("mocked", mockable(0));
+        });
+
+        assert_eq!("not mocked 0", mockable(0));
+        assert!(!mockable.is_mocked());
+    }
+
+    #[test]
+    fn when_body_sets_up_mock_then_it_is_discarded() {
+        mockable.mock_safe(mock_returning("mocked 1"));
+
+        mockable.with_mock(mock_returning("mocked 2"), || {
+            mockable.mock_safe(mock_returning("mocked 3"));
+        });
+
+        assert_eq!("mocked 1", mockable(0));
+    }
+
+    #[test]
+    fn when_body_panics_then_restores_mock() {
+        mockable.mock_safe(mock_returning("mocked 1"));
+
+        let result = std::panic::catch_unwind(|| {
+            mockable.with_mock(mock_returning("mocked 2"), || panic!("body panic"));
+        });
+
+        assert!(result.is_err());
+        assert_eq!("mocked 1", mockable(0));
+    }
+
+    #[test]
+    fn when_body_returns_then_returns_its_result() {
+        let result = mockable.with_mock(mock_returning("mocked"), || mockable(0));
+
+        assert_eq!("mocked", result);
+    }
+
+    #[test]
+    fn when_conditional_mocks_replaced_then_restores_them() {
+        mockable.mock_safe_when(|&(x,)| x == 1, mock_returning("mocked 1"));
+
+        mockable.with_mock(mock_returning("mocked 2"), || {
+            mockable.mock_safe_when(|&(x,)| x == 3, mock_returning("mocked 3"));
+            assert_eq!("mocked 3", mockable(3));
+            assert_eq!("not mocked 1", mockable(1));
+        });
+        mockable.mock_safe_when(|&(x,)| x == 2, mock_returning("mocked 2"));
+
+        assert_eq!("mocked 1", mockable(1));
+        assert_eq!("mocked 2", mockable(2));
+        assert_eq!("not mocked 3", mockable(3));
+    }
+}
+
+mod mock_context {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_2() -> &'static str {
+        "not mocked 2"
+    }
+
+    #[mockable]
+    fn mockable_string() -> String {
+        "not mocked".to_string()
+    }
+
+    #[test]
+    fn context_mocks_mock_only_inside_run_closure() {
+        assert_eq!("not mocked 1", mockable_1());
+        assert_eq!("not mocked 2", mockable_2());
+        MockContext::new()
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1"))
+            .run(|| {
+                assert_eq!("mocked 1", mockable_1());
+                assert_eq!("not mocked 2", mockable_2());
+            });
+        assert_eq!("not mocked 1", mockable_1());
+        assert_eq!("not mocked 2", mockable_2());
+    }
+
+    #[test]
+    fn context_mocks_with_no_mocks_have_no_effect() {
+        assert_eq!("not mocked 1", mockable_1());
+        MockContext::new().run(|| {
+            assert_eq!("not mocked 1", mockable_1());
+        });
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn context_mocks_with_multiple_mocks_of_the_same_function_use_only_last_mock() {
+        assert_eq!("not mocked 1", mockable_1());
+        MockContext::new()
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1 A"))
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1 B"))
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1 C"))
+            .run(|| {
+                assert_eq!("mocked 1 C", mockable_1());
+            });
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn nested_context_mocks_shadow_outside_mocks_only_inside_run_closure() {
+        assert_eq!("not mocked 1", mockable_1());
+        assert_eq!("not mocked 2", mockable_2());
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+        assert_eq!("mocked 1", mockable_1());
+        assert_eq!("not mocked 2", mockable_2());
+        MockContext::new()
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1 context 1"))
+            .mock_safe(mockable_2, || MockResult::Return("mocked 2 context 1"))
+            .run(|| {
+                assert_eq!("mocked 1 context 1", mockable_1());
+                assert_eq!("mocked 2 context 1", mockable_2());
+                MockContext::new()
+                    .mock_safe(mockable_1, || MockResult::Return("mocked 1 context 2"))
+                    .run(|| {
+                        assert_eq!("mocked 1 context 2", mockable_1());
+                        assert_eq!("mocked 2 context 1", mockable_2());
+                    });
+                assert_eq!("mocked 1 context 1", mockable_1());
+                assert_eq!("mocked 2 context 1", mockable_2());
+            });
+        assert_eq!("mocked 1", mockable_1());
+        assert_eq!("not mocked 2", mockable_2());
+    }
+
+    #[test]
+    fn calling_function_with_shadowed_mock_from_inside_mock_closure_calls_shadowed_mock() {
+        assert_eq!("not mocked", mockable_string());
+        mockable_string.mock_safe(|| MockResult::Return(format!("{}, mocked", mockable_string())));
+        assert_eq!("not mocked, mocked", mockable_string());
+        MockContext::new()
+            .mock_safe(mockable_string, || {
+                MockResult::Return(format!("{}, mocked context", mockable_string()))
+            })
+            .run(|| {
+                assert_eq!("not mocked, mocked, mocked context", mockable_string());
+            });
+        assert_eq!("not mocked, mocked", mockable_string());
+    }
+}
+
+mod mock_safe_times {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_add(x: u32, y: u32) -> u32 {
+        x + y
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock_only_given_number_of_times() {
+        mockable_1.mock_safe_times(2, || MockResult::Return("mocked 1"));
+
+        assert_eq!("mocked 1", mockable_1());
+        assert_eq!("mocked 1", mockable_1());
+        assert_eq!("not mocked 1", mockable_1());
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_mocked_zero_times_then_runs_normally() {
+        mockable_1.mock_safe_times(0, || MockResult::Return("mocked 1"));
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_mock_is_used_up_then_runs_with_original_args() {
+        mockable_add.mock_safe_times(1, |x, y| MockResult::Continue((x * 10, y * 10)));
+
+        assert_eq!(30, mockable_add(1, 2));
+        assert_eq!(3, mockable_add(1, 2));
+    }
+
+    #[test]
+    fn when_mocked_again_then_limit_is_discarded() {
+        mockable_1.mock_safe_times(1, || MockResult::Return("mocked 1 A"));
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1 B"));
+
+        assert_eq!("mocked 1 B", mockable_1());
+        assert_eq!("mocked 1 B", mockable_1());
+    }
+
+    #[test]
+    fn when_mocks_are_cleared_then_runs_normally() {
+        mockable_1.mock_safe_times(2, || MockResult::Return("mocked 1"));
+
+        clear_mocks();
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+}
+
+mod call_count {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_2() -> &'static str {
+        "not mocked 2"
+    }
+
+    #[mockable]
+    fn generic<T>(_: T) -> &'static str {
+        "not mocked"
+    }
+
+    #[test]
+    fn when_not_called_then_returns_zero() {
+        assert_eq!(0, mockable_1.call_count());
+    }
+
+    #[test]
+    fn when_nothing_mocked_then_does_not_count_calls() {
+        mockable_1();
+
+        assert_eq!(0, mockable_1.call_count());
+    }
+
+    #[test]
+    fn when_not_mocked_but_other_fn_mocked_then_counts_calls() {
+        mockable_2.mock_safe(|| MockResult::Return("mocked 2"));
+
+        mockable_1();
+        mockable_1();
+
+        assert_eq!(2, mockable_1.call_count());
+        assert_eq!(0, mockable_2.call_count());
+    }
+
+    #[test]
+    fn when_not_mocked_and_reset_then_counts_calls() {
+        mockable_1.reset_call_count();
+
+        mockable_1();
+        mockable_1();
+
+        assert_eq!(2, mockable_1.call_count());
+        assert_eq!(0, mockable_2.call_count());
+    }
+
+    #[test]
+    fn when_mocked_then_counts_both_returned_and_continued_calls() {
+        let mut x = 0;
+        mockable_1.mock_safe(move || {
+            x += 1;
+            match x {
+                1 => MockResult::Return("mocked 1"),
+                _ => MockResult::Continue(()),
+            }
+        });
+
+        assert_eq!("mocked 1", mockable_1());
+        assert_eq!("not mocked 1", mockable_1());
+        assert_eq!(2, mockable_1.call_count());
+    }
+
+    #[test]
+    fn when_mocked_again_then_count_is_reset() {
+        mockable_1();
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+        assert_eq!(0, mockable_1.call_count());
+
+        mockable_1();
+
+        assert_eq!(1, mockable_1.call_count());
+    }
+
+    #[test]
+    fn when_reset_then_counts_calls_of_each_phase_and_keeps_mock() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+        mockable_1();
+        mockable_1();
+        assert_eq!(2, mockable_1.call_count());
+
+        mockable_1.reset_call_count();
+        assert_eq!(0, mockable_1.call_count());
+
+        assert_eq!("mocked 1", mockable_1());
+        assert_eq!(1, mockable_1.call_count());
+    }
+
+    #[test]
+    fn when_reset_then_other_functions_are_not_affected() {
+        mockable_2.reset_call_count();
+        mockable_1();
+        mockable_2();
+
+        mockable_1.reset_call_count();
+
+        assert_eq!(0, mockable_1.call_count());
+        assert_eq!(1, mockable_2.call_count());
+    }
+
+    #[test]
+    fn generic_variants_are_counted_separately() {
+        generic::<u32>.reset_call_count();
+        generic(1u32);
+        generic(1u32);
+        generic("1");
+
+        assert_eq!(2, generic::<u32>.call_count());
+        assert_eq!(1, generic::<&str>.call_count());
+        assert_eq!(0, generic::<i32>.call_count());
+    }
+
+    #[test]
+    fn calls_in_other_threads_are_not_counted() {
+        std::thread::spawn(mockable_1).join().unwrap();
+
+        assert_eq!(0, mockable_1.call_count());
+    }
+}
+
+mod mock_closure {
+    use super::*;
+
+    struct Handler<F: Fn(u32) -> u32> {
+        callback: F,
+    }
+
+    fn boxed_handler(key: &str) -> Box<dyn Fn(u32) -> u32> {
+        Box::new(MockableClosure::new(key, |x: u32| x * 2))
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_closure() {
+        let handler = Handler {
+            callback: MockableClosure::new("double", |x: u32| x * 2),
+        };
+
+        assert_eq!(6, (handler.callback)(3));
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock() {
+        let handler = Handler {
+            callback: MockableClosure::new("double", |x: u32| x * 2),
+        };
+
+        mock_closure("double", |x: u32| MockResult::Return(x * 10));
+
+        assert_eq!(30, (handler.callback)(3));
+    }
+
+    #[test]
+    fn when_mocked_with_continue_then_runs_closure_with_modified_args() {
+        let closure = boxed_handler("double");
+
+        mock_closure("double", |x: u32| MockResult::<_, u32>::Continue((x + 1,)));
+
+        assert_eq!(8, closure(3));
+    }
+
+    #[test]
+    fn when_other_key_mocked_then_runs_closure() {
+        let closure = boxed_handler("double");
+
+        mock_closure("triple", |x: u32| MockResult::Return(x * 10));
+
+        assert_eq!(6, closure(3));
+    }
+
+    #[test]
+    fn when_mocked_with_other_signature_then_runs_closure() {
+        let closure = boxed_handler("double");
+
+        mock_closure("double", |x: u64| MockResult::Return(x * 10));
+
+        assert_eq!(6, closure(3));
+    }
+
+    #[test]
+    fn when_mocked_with_other_signature_of_same_type_name_then_runs_closure() {
+        let closure = {
+            struct Value(u32);
+            let closure = MockableClosure::new("value", |value: Value| value.0);
+            move |x| closure(Value(x))
+        };
+        {
+            // Has the same type name as the other `Value`
+            struct Value(u64);
+            mock_closure("value", |value: Value| {
+                MockResult::Return(value.0 as u32 * 10)
+            });
+        }
+
+        assert_eq!(3, closure(3));
+    }
+
+    #[test]
+    fn when_mocks_cleared_then_runs_closure() {
+        let closure = boxed_handler("double");
+        mock_closure("double", |x: u32| MockResult::Return(x * 10));
+
+        clear_mocks();
+
+        assert_eq!(6, closure(3));
+    }
+
+    #[test]
+    fn mutable_closures_can_be_mocked() {
+        fn call(mut closure: impl FnMut() -> u32) -> u32 {
+            closure()
+        }
+        let mut calls = 0;
+        mock_closure("counter", || MockResult::Return(10u32));
+
+        let result = call(MockableClosure::new("counter", || {
+            calls += 1;
+            calls
+        }));
+
+        assert_eq!(10, result);
+        assert_eq!(0, calls);
+    }
+}
+
+mod mock_safe_when {
+    use super::*;
+
+    #[mockable]
+    fn get_string(id: u32) -> String {
+        format!("not mocked {}", id)
+    }
+
+    #[test]
+    fn when_matcher_accepts_args_then_runs_mock() {
+        get_string.mock_safe_when(
+            |&(id,)| id == 1,
+            |id| MockResult::Return(format!("mocked {}", id)),
+        );
+
+        assert_eq!("mocked 1", get_string(1));
+        assert_eq!("not mocked 2", get_string(2));
+    }
+
+    #[test]
+    fn when_mocked_multiple_times_then_runs_first_matching_mock() {
+        get_string.mock_safe_when(
+            |&(id,)| id == 1,
+            |_| MockResult::Return("mocked A".to_string()),
+        );
+        get_string.mock_safe_when(
+            |&(id,)| id < 3,
+            |_| MockResult::Return("mocked B".to_string()),
+        );
+        get_string.mock_safe_when(
+            |&(id,)| id == 2,
+            |_| MockResult::Return("mocked C".to_string()),
+        );
+
+        assert_eq!("mocked A", get_string(1));
+        assert_eq!("mocked B", get_string(2));
+        assert_eq!("not mocked 3", get_string(3));
+    }
+
+    #[test]
+    fn when_mock_sets_up_conditional_mock_then_next_calls_use_it() {
+        get_string.mock_safe_when(
+            |&(id,)| id == 1,
+            |_| {
+                get_string.mock_safe_when(
+                    |&(id,)| id == 2,
+                    |_| MockResult::Return("mocked 2".to_string()),
+                );
+                MockResult::Return("mocked 1".to_string())
+            },
+        );
+
+        assert_eq!("not mocked 2", get_string(2));
+        assert_eq!("mocked 1", get_string(1));
+        assert_eq!("mocked 2", get_string(2));
+    }
+
+    #[test]
+    fn when_mock_continues_then_runs_with_modified_args() {
+        get_string.mock_safe_when(|&(id,)| id == 1, |id| MockResult::Continue((id + 10,)));
+
+        assert_eq!("not mocked 11", get_string(1));
+    }
+
+    #[test]
+    fn when_mocked_after_regular_mock_then_replaces_it() {
+        get_string.mock_safe(|_| MockResult::Return("mocked".to_string()));
+        get_string.mock_safe_when(
+            |&(id,)| id == 1,
+            |_| MockResult::Return("mocked 1".to_string()),
+        );
+
+        assert_eq!("mocked 1", get_string(1));
+        assert_eq!("not mocked 2", get_string(2));
+    }
+
+    #[test]
+    fn when_regular_mock_is_set_up_then_replaces_all() {
+        get_string.mock_safe_when(
+            |&(id,)| id == 1,
+            |_| MockResult::Return("mocked 1".to_string()),
+        );
+        get_string.mock_safe(|_| MockResult::Return("mocked".to_string()));
+        get_string.mock_safe_when(
+            |&(id,)| id == 2,
+            |_| MockResult::Return("mocked 2".to_string()),
+        );
+
+        assert_eq!("not mocked 1", get_string(1));
+        assert_eq!("mocked 2", get_string(2));
+    }
+
+    #[test]
+    fn when_cleared_then_runs_normally() {
+        get_string.mock_safe_when(
+            |&(id,)| id == 1,
+            |_| MockResult::Return("mocked 1".to_string()),
+        );
+        get_string.clear_mock();
+        get_string.mock_safe_when(
+            |&(id,)| id == 2,
+            |_| MockResult::Return("mocked 2".to_string()),
+        );
+
+        assert_eq!("not mocked 1", get_string(1));
+        assert_eq!("mocked 2", get_string(2));
+    }
+}
+
+mod mock_returns_seq {
+    use super::*;
+
+    #[mockable]
+    fn get_string(id: u32) -> String {
+        format!("not mocked {}", id)
+    }
+
+    #[test]
+    fn when_mocked_then_returns_values_in_order() {
+        get_string.mock_returns_seq(vec!["mocked 1".to_string(), "mocked 2".to_string()]);
+
+        assert_eq!("mocked 1", get_string(1));
+        assert_eq!("mocked 2", get_string(2));
+    }
+
+    #[test]
+    fn when_values_are_used_up_then_runs_normally() {
+        get_string.mock_returns_seq(vec!["mocked 1".to_string()]);
+
+        assert_eq!("mocked 1", get_string(1));
+        assert_eq!("not mocked 2", get_string(2));
+        assert_eq!("not mocked 3", get_string(3));
+    }
+
+    #[test]
+    fn when_mocked_with_no_values_then_runs_normally() {
+        get_string.mock_returns_seq(vec![]);
+
+        assert_eq!("not mocked 1", get_string(1));
+    }
+
+    #[test]
+    fn when_mocked_again_then_previous_values_are_discarded() {
+        get_string.mock_returns_seq(vec!["mocked A".to_string(), "mocked B".to_string()]);
+        get_string.mock_returns_seq(vec!["mocked C".to_string()]);
+
+        assert_eq!("mocked C", get_string(1));
+        assert_eq!("not mocked 2", get_string(2));
+    }
+}
+
+mod spy {
+    use super::*;
+
+    #[mockable]
+    fn get_string(id: u32, name: String) -> String {
+        format!("not mocked {} {}", id, name)
+    }
+
+    #[test]
+    fn when_not_called_then_records_no_calls() {
+        let recorder = get_string.spy();
+
+        assert!(recorder.calls().is_empty());
+    }
+
+    #[test]
+    fn when_called_then_runs_normally_and_records_args() {
+        let recorder = get_string.spy();
+
+        assert_eq!("not mocked 1 a", get_string(1, "a".to_string()));
+        assert_eq!("not mocked 2 b", get_string(2, "b".to_string()));
+
+        assert_eq!(
+            vec![(1, "a".to_string()), (2, "b".to_string())],
+            recorder.calls()
+        );
+    }
+
+    #[test]
+    fn when_mocked_again_then_recorded_calls_are_discarded() {
+        let recorder = get_string.spy();
+        get_string(1, "a".to_string());
+
+        get_string.mock_safe(|_, _| MockResult::Return("mocked".to_string()));
+        get_string(2, "b".to_string());
+
+        assert!(recorder.calls().is_empty());
+    }
+
+    #[test]
+    fn when_spied_again_then_old_recorder_is_discarded() {
+        let recorder_1 = get_string.spy();
+        get_string(1, "a".to_string());
+        let recorder_2 = get_string.spy();
+        get_string(2, "b".to_string());
+
+        assert!(recorder_1.calls().is_empty());
+        assert_eq!(vec![(2, "b".to_string())], recorder_2.calls());
+    }
+
+    #[test]
+    fn spy_counts_calls() {
+        get_string(1, "a".to_string());
+        let _recorder = get_string.spy();
+        get_string(2, "b".to_string());
+
+        assert_eq!(1, get_string.call_count());
+    }
+}
+
+mod require_mocks {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_2() -> &'static str {
+        "not mocked 2"
+    }
+
+    #[test]
+    #[should_panic(expected = "mockable_1' was called without a mock")]
+    fn when_required_and_not_mocked_then_panics() {
+        require_mocks(true);
+
+        mockable_1();
+    }
+
+    #[test]
+    fn when_required_and_mocked_then_runs_mock() {
+        require_mocks(true);
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+
+        assert_eq!("mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_required_and_mock_continues_then_runs_normally() {
+        require_mocks(true);
+        mockable_1.mock_safe(|| MockResult::Continue(()));
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_required_and_mocked_in_context_then_runs_mock() {
+        require_mocks(true);
+
+        MockContext::new()
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1"))
+            .run(|| assert_eq!("mocked 1", mockable_1()));
+    }
+
+    #[test]
+    fn when_required_and_called_inside_own_mock_then_runs_normally() {
+        require_mocks(true);
+        mockable_1.mock_safe(|| MockResult::Return(mockable_1()));
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    #[should_panic(expected = "mockable_2' was called without a mock")]
+    fn when_required_and_calling_other_not_mocked_function_inside_mock_then_panics() {
+        require_mocks(true);
+        mockable_1.mock_safe(|| MockResult::Return(mockable_2()));
+
+        mockable_1();
+    }
+
+    #[test]
+    fn when_no_longer_required_then_runs_normally() {
+        require_mocks(true);
+        require_mocks(false);
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    struct Struct<T>(T);
+
+    trait Trait {
+        fn trait_default(&self) -> &'static str {
+            "not mocked default"
+        }
+
+        fn trait_method(&self) -> &'static str;
+    }
+
+    #[mockable]
+    trait MockableTrait {
+        fn mockable_trait_default(&self) -> &'static str {
+            "not mocked default"
+        }
+    }
+
+    #[mockable]
+    impl<T> Struct<T> {
+        fn method(&self) -> &'static str {
+            "not mocked method"
+        }
+    }
+
+    #[mockable]
+    impl<T> Trait for Struct<T> {
+        fn trait_method(&self) -> &'static str {
+            "not mocked trait method"
+        }
+    }
+
+    impl<T> MockableTrait for Struct<T> {}
+
+    #[test]
+    #[should_panic(expected = "'mocking::require_mocks::mockable_1' was called without a mock")]
+    fn when_required_and_not_mocked_then_panics_with_full_fn_name() {
+        require_mocks(true);
+
+        mockable_1();
+    }
+
+    #[test]
+    #[should_panic(expected = "'mocking::require_mocks::Struct::method' was called without a mock")]
+    fn when_required_and_struct_method_not_mocked_then_panics_with_full_method_name() {
+        require_mocks(true);
+
+        Struct(1).method();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "'mocking::require_mocks::<Struct as Trait>::trait_method' was called without a mock"
+    )]
+    fn when_required_and_trait_method_not_mocked_then_panics_with_full_method_name() {
+        require_mocks(true);
+
+        Struct(1).trait_method();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "'mocking::require_mocks::MockableTrait::mockable_trait_default' was called without a mock"
+    )]
+    fn when_required_and_trait_default_method_not_mocked_then_panics_with_full_method_name() {
+        require_mocks(true);
+
+        Struct(1).mockable_trait_default();
+    }
+
+    #[test]
+    fn when_required_and_called_not_mockable_method_then_runs_normally() {
+        require_mocks(true);
+
+        assert_eq!("not mocked default", Struct(1).trait_default());
+    }
+
+    #[test]
+    fn when_required_in_other_thread_then_runs_normally() {
+        std::thread::spawn(|| require_mocks(true)).join().unwrap();
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+}
+
+mod call_real {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1(x: u32) -> u32 {
+        x * 2
+    }
+
+    #[mockable]
+    fn mockable_recursive(x: u32) -> u32 {
+        match x {
+            0 => 0,
+            _ => x + mockable_recursive(x - 1),
+        }
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(4, mockable_1.call_real((2,)));
+    }
+
+    #[test]
+    fn when_mocked_then_runs_normally() {
+        mockable_1.mock_safe(|_| MockResult::Return(0));
+
+        assert_eq!(4, mockable_1.call_real((2,)));
+        assert_eq!(0, mockable_1(2));
+    }
+
+    #[test]
+    fn when_called_inside_mock_then_returns_real_result() {
+        mockable_1.mock_safe(|x| MockResult::Return(mockable_1.call_real((x,)) + 1));
+
+        assert_eq!(5, mockable_1(2));
+    }
+
+    #[test]
+    fn when_called_inside_context_mock_then_bypasses_thread_mock() {
+        mockable_1.mock_safe(|_| MockResult::Return(0));
+
+        MockContext::new()
+            .mock_safe(mockable_1, |x| {
+                MockResult::Return(mockable_1.call_real((x,)) + 1)
+            })
+            .run(|| assert_eq!(5, mockable_1(2)));
+    }
+
+    #[test]
+    fn when_called_then_recursive_calls_are_mocked() {
+        mockable_recursive.mock_safe(|x| match x {
+            2 => MockResult::Return(100),
+            _ => MockResult::Continue((x,)),
+        });
+
+        assert_eq!(103, mockable_recursive.call_real((3,)));
+    }
+
+    #[test]
+    fn when_called_then_call_is_not_counted() {
+        mockable_1.mock_safe(|x| MockResult::Return(mockable_1.call_real((x,))));
+
+        mockable_1(2);
+
+        assert_eq!(1, mockable_1.call_count());
+    }
+
+    #[test]
+    fn when_mocks_required_then_runs_normally() {
+        require_mocks(true);
+
+        assert_eq!(4, mockable_1.call_real((2,)));
+    }
+}
+
+mod mock_safe_global {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc, Barrier, Mutex, MutexGuard, PoisonError};
+
+    // Global mocks are shared between tests running in parallel, so every test uses its own function
+    // and tests clearing global mocks must not run at the same time as others
+    static GLOBAL_MOCKS_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_global_mocks() -> MutexGuard<'static, ()> {
+        GLOBAL_MOCKS_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_2() -> &'static str {
+        "not mocked 2"
+    }
+
+    #[mockable]
+    fn mockable_3() -> &'static str {
+        "not mocked 3"
+    }
+
+    #[mockable]
+    fn mockable_4(x: u32) -> u32 {
+        x * 2
+    }
+
+    #[mockable]
+    fn mockable_5() -> &'static str {
+        "not mocked 5"
+    }
+
+    #[mockable]
+    fn mockable_6() -> &'static str {
+        "not mocked 6"
+    }
+
+    #[mockable]
+    fn mockable_7() -> &'static str {
+        "not mocked 7"
+    }
+
+    #[mockable]
+    fn mockable_8() -> &'static str {
+        "not mocked 8"
+    }
+
+    #[mockable]
+    fn mockable_9() -> &'static str {
+        "not mocked 9"
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock_in_all_threads() {
+        let _lock = lock_global_mocks();
+        mockable_1.mock_safe_global(|| MockResult::Return("mocked 1"));
+
+        let results: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(mockable_1))
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert_eq!(vec!["mocked 1"; 4], results);
+        assert_eq!("mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_mocked_in_thread_then_thread_mock_takes_precedence() {
+        let _lock = lock_global_mocks();
+        mockable_2.mock_safe_global(|| MockResult::Return("mocked globally 2"));
+        mockable_2.mock_safe(|| MockResult::Return("mocked in thread 2"));
+
+        assert_eq!("mocked in thread 2", mockable_2());
+        assert_eq!(
+            "mocked globally 2",
+            std::thread::spawn(mockable_2).join().unwrap()
+        );
+    }
+
+    #[test]
+    fn when_mocked_and_cleared_then_runs_normally() {
+        let _lock = lock_global_mocks();
+        mockable_3.mock_safe_global(|| MockResult::Return("mocked 3"));
+
+        clear_global_mocks();
+
+        assert_eq!("not mocked 3", mockable_3());
+    }
+
+    #[test]
+    fn when_mock_calls_function_then_it_runs_normally() {
+        let _lock = lock_global_mocks();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mock_calls = calls.clone();
+        mockable_4.mock_safe_global(move |x| {
+            mock_calls.fetch_add(1, Ordering::SeqCst);
+            MockResult::Return(mockable_4(x) + 1)
+        });
+
+        let handle = std::thread::spawn(|| mockable_4(2));
+
+        assert_eq!(5, mockable_4(2));
+        assert_eq!(5, handle.join().unwrap());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn when_mock_panics_then_next_calls_run_mock() {
+        let _lock = lock_global_mocks();
+        let mut panic = true;
+        mockable_5.mock_safe_global(move || {
+            if std::mem::replace(&mut panic, false) {
+                panic!("mock panic");
+            }
+            MockResult::Return("mocked 5")
+        });
+
+        assert!(std::thread::spawn(mockable_5).join().is_err());
+        assert_eq!("mocked 5", mockable_5());
+    }
+
+    #[test]
+    fn when_mock_panics_then_other_global_mocks_can_be_used_and_cleared() {
+        let _lock = lock_global_mocks();
+        mockable_8.mock_safe_global(|| panic!("mock panic"));
+        mockable_9.mock_safe_global(|| MockResult::Return("mocked 9"));
+
+        let results: Vec<_> = (0..4).map(|_| std::thread::spawn(mockable_8)).collect();
+        assert!(results.into_iter().all(|handle| handle.join().is_err()));
+
+        assert_eq!("mocked 9", std::thread::spawn(mockable_9).join().unwrap());
+        mockable_8.mock_safe_global(|| MockResult::Return("mocked 8"));
+        assert_eq!("mocked 8", mockable_8());
+
+        clear_global_mocks();
+
+        assert_eq!("not mocked 8", mockable_8());
+        assert_eq!("not mocked 9", mockable_9());
+    }
+
+    #[test]
+    fn when_mocked_once_then_runs_mock_in_exactly_one_of_racing_threads() {
+        let _lock = lock_global_mocks();
+        mockable_6.mock_safe_global_once(|| MockResult::Return("mocked 6"));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let mut results: Vec<_> = (0..8)
+            .map(|_| {
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    mockable_6()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        results.sort();
+
+        let mut expected = vec!["not mocked 6"; 7];
+        expected.insert(0, "mocked 6");
+        assert_eq!(expected, results);
+        assert_eq!("not mocked 6", mockable_6());
+    }
+
+    #[test]
+    fn when_mocked_once_then_other_threads_do_not_wait_for_mock() {
+        let _lock = lock_global_mocks();
+        let (taken_sender, taken_receiver) = mpsc::channel();
+        let (release_sender, release_receiver) = mpsc::channel::<()>();
+        mockable_7.mock_safe_global_once(move || {
+            taken_sender.send(()).unwrap();
+            release_receiver.recv().unwrap();
+            MockResult::Return("mocked 7")
+        });
+
+        let handle = std::thread::spawn(mockable_7);
+        taken_receiver.recv().unwrap();
+
+        assert_eq!("not mocked 7", mockable_7());
+        release_sender.send(()).unwrap();
+        assert_eq!("mocked 7", handle.join().unwrap());
+    }
+}
+
+mod mock_trait_method {
+    use super::*;
+
+    trait Trait {
+        fn method(&self, arg: u32) -> String;
+
+        fn consume(self) -> String
+        where
+            Self: Sized;
+    }
+
+    struct Struct1;
+
+    #[mockable]
+    impl Trait for Struct1 {
+        fn method(&self, arg: u32) -> String {
+            format!("not mocked 1 {}", arg)
+        }
+
+        fn consume(self) -> String {
+            "not mocked 1".to_string()
+        }
+    }
+
+    struct Struct2;
+
+    #[mockable]
+    impl Trait for Struct2 {
+        fn method(&self, arg: u32) -> String {
+            format!("not mocked 2 {}", arg)
+        }
+
+        fn consume(self) -> String {
+            "not mocked 2".to_string()
+        }
+    }
+
+    trait Generic<T> {
+        fn generic(&self, arg: T) -> String;
+    }
+
+    #[mockable]
+    impl Generic<u32> for Struct1 {
+        fn generic(&self, arg: u32) -> String {
+            format!("not mocked u32 {}", arg)
+        }
+    }
+
+    #[mockable]
+    impl Generic<bool> for Struct1 {
+        fn generic(&self, arg: bool) -> String {
+            format!("not mocked bool {}", arg)
+        }
+    }
+
+    mod other {
+        use super::*;
+
+        pub trait Trait {
+            fn method(&self, arg: u32) -> String;
+        }
+
+        #[mockable]
+        impl Trait for Struct1 {
+            fn method(&self, arg: u32) -> String {
+                format!("not mocked other {}", arg)
+            }
+        }
+    }
+
+    mod nested {
+        pub trait Trait {
+            fn method(&self, arg: u32) -> String;
+        }
+    }
+
+    #[mockable]
+    impl nested::Trait for Struct2 {
+        fn method(&self, arg: u32) -> String {
+            format!("not mocked nested {}", arg)
+        }
+    }
+
+    #[mockable]
+    impl ::std::fmt::Display for Struct2 {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "not mocked")
+        }
+    }
+
+    fn call_all(arg: u32) -> Vec<String> {
+        let implementors: Vec<Box<dyn Trait>> = vec![Box::new(Struct1), Box::new(Struct2)];
+        implementors
+            .iter()
+            .map(|implementor| implementor.method(arg))
+            .collect()
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(vec!["not mocked 1 3", "not mocked 2 3"], call_all(3));
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock_for_all_implementors() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |arg: u32| {
+            MockResult::Return(format!("mocked {}", arg))
+        });
+
+        assert_eq!(vec!["mocked 3", "mocked 3"], call_all(3));
+    }
+
+    #[test]
+    fn when_mocked_with_continue_then_runs_with_modified_args() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |arg: u32| {
+            MockResult::<_, String>::Continue((arg + 1,))
+        });
+
+        assert_eq!(vec!["not mocked 1 4", "not mocked 2 4"], call_all(3));
+    }
+
+    #[test]
+    fn when_implementor_mocked_then_implementor_mock_takes_precedence() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |_: u32| {
+            MockResult::Return("mocked trait".to_string())
+        });
+        Struct1::method.mock_safe(|_, _| MockResult::Return("mocked 1".to_string()));
+
+        assert_eq!(vec!["mocked 1", "mocked trait"], call_all(3));
+    }
+
+    #[test]
+    fn when_receiver_taken_by_value_then_runs_mock() {
+        mock_trait_method(concat!(module_path!(), "::Trait::consume"), || {
+            MockResult::Return("mocked".to_string())
+        });
+
+        assert_eq!("mocked", Struct1.consume());
+        assert_eq!("mocked", Struct2.consume());
+    }
+
+    #[test]
+    fn when_mocked_other_method_then_runs_normally() {
+        mock_trait_method(concat!(module_path!(), "::Trait::consume"), || {
+            MockResult::Return("mocked".to_string())
+        });
+
+        assert_eq!(vec!["not mocked 1 3", "not mocked 2 3"], call_all(3));
+    }
+
+    #[test]
+    fn when_mocked_with_different_signature_then_runs_normally() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |arg: u64| {
+            MockResult::Return(format!("mocked {}", arg))
+        });
+
+        assert_eq!(vec!["not mocked 1 3", "not mocked 2 3"], call_all(3));
+    }
+
+    #[test]
+    fn when_mock_calls_method_then_it_runs_normally() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |arg: u32| {
+            MockResult::Return(format!("mocked {}", Struct1.method(arg)))
+        });
+
+        assert_eq!("mocked not mocked 1 3", Struct2.method(3));
+    }
+
+    #[test]
+    fn when_mocked_then_calls_are_counted_per_implementor() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |_: u32| {
+            MockResult::Return("mocked".to_string())
+        });
+
+        call_all(3);
+        Struct1.method(3);
+
+        assert_eq!(2, Struct1::method.call_count());
+        assert_eq!(1, Struct2::method.call_count());
+    }
+
+    #[test]
+    fn when_mocked_and_cleared_then_runs_normally() {
+        mock_trait_method(concat!(module_path!(), "::Trait::method"), |_: u32| {
+            MockResult::Return("mocked".to_string())
+        });
+
+        clear_mocks();
+
+        assert_eq!(vec!["not mocked 1 3", "not mocked 2 3"], call_all(3));
+    }
+
+    #[test]
+    fn when_trait_of_same_name_in_other_module_mocked_then_runs_normally() {
+        mock_trait_method(
+            concat!(module_path!(), "::other::Trait::method"),
+            |arg: u32| MockResult::Return(format!("mocked {}", arg)),
+        );
+
+        assert_eq!(vec!["not mocked 1 3", "not mocked 2 3"], call_all(3));
+        assert_eq!("mocked 3", other::Trait::method(&Struct1, 3));
+    }
+
+    #[test]
+    fn when_trait_path_written_in_impl_then_its_mock_is_named_with_it() {
+        mock_trait_method(
+            concat!(module_path!(), "::nested::Trait::method"),
+            |arg: u32| MockResult::Return(format!("mocked {}", arg)),
+        );
+
+        assert_eq!(vec!["not mocked 1 3", "not mocked 2 3"], call_all(3));
+        assert_eq!("mocked 3", nested::Trait::method(&Struct2, 3));
+    }
+
+    #[test]
+    fn when_trait_path_with_leading_colon_then_its_mock_is_named_with_path() {
+        mock_trait_method("std::fmt::Display::fmt", |f: &mut std::fmt::Formatter| {
+            MockResult::Return(write!(f, "mocked"))
+        });
+
+        assert_eq!("mocked", Struct2.to_string());
+    }
+
+    #[test]
+    fn when_generic_trait_mocked_then_runs_mock_only_for_its_generic_args() {
+        mock_trait_method(concat!(module_path!(), "::Generic::generic"), |arg: u32| {
+            MockResult::Return(format!("mocked {}", arg))
+        });
+
+        assert_eq!("mocked 3", Struct1.generic(3u32));
+        assert_eq!("not mocked bool true", Struct1.generic(true));
+    }
+}
+
+mod mock_return_result {
+    use super::*;
+
+    #[mockable]
+    fn mockable(arg: &str) -> Result<String, String> {
+        Ok(format!("not mocked {}", arg))
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct NotClone;
+
+    #[mockable]
+    fn mockable_not_clone_error() -> Result<u32, NotClone> {
+        Err(NotClone)
+    }
+
+    #[test]
+    fn when_mocked_ok_then_returns_ok_on_every_call() {
+        mockable.mock_return_ok("mocked".to_string());
+
+        assert_eq!(Ok("mocked".to_string()), mockable("a"));
+        assert_eq!(Ok("mocked".to_string()), mockable("b"));
+    }
+
+    #[test]
+    fn when_mocked_err_then_returns_err_on_every_call() {
+        mockable.mock_return_err("mocked".to_string());
+
+        assert_eq!(Err("mocked".to_string()), mockable("a"));
+        assert_eq!(Err("mocked".to_string()), mockable("b"));
+    }
+
+    #[test]
+    fn when_mocked_ok_with_not_clone_error_then_returns_ok() {
+        mockable_not_clone_error.mock_return_ok(1);
+
+        assert_eq!(Ok(1), mockable_not_clone_error());
+    }
+
+    #[test]
+    fn when_mocked_then_calls_are_counted() {
+        mockable.mock_return_ok("mocked".to_string());
+
+        let _ = mockable("a");
+
+        assert_eq!(1, mockable.call_count());
+    }
+}
+
+mod mock_return_option {
+    use super::*;
+
+    #[mockable]
+    fn mockable(arg: &str) -> Option<String> {
+        Some(format!("not mocked {}", arg))
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct NotClone;
+
+    #[mockable]
+    fn mockable_not_clone() -> Option<NotClone> {
+        Some(NotClone)
+    }
+
+    #[test]
+    fn when_mocked_some_then_returns_some_on_every_call() {
+        mockable.mock_return_some("mocked".to_string());
+
+        assert_eq!(Some("mocked".to_string()), mockable("a"));
+        assert_eq!(Some("mocked".to_string()), mockable("b"));
+    }
+
+    #[test]
+    fn when_mocked_none_then_returns_none_on_every_call() {
+        mockable.mock_return_none();
+
+        assert_eq!(None, mockable("a"));
+        assert_eq!(None, mockable("b"));
+    }
+
+    #[test]
+    fn when_mocked_none_with_not_clone_value_then_returns_none() {
+        mockable_not_clone.mock_return_none();
+
+        assert_eq!(None, mockable_not_clone());
+    }
+}
+
+mod mock_safe_scoped {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_2() -> &'static str {
+        "not mocked 2"
+    }
+
+    #[test]
+    fn when_guard_alive_then_runs_mock() {
+        let _guard = mockable_1.mock_safe_scoped(|| MockResult::Return("mocked 1"));
+
+        assert_eq!("mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_guard_dropped_then_runs_normally() {
+        let guard = mockable_1.mock_safe_scoped(|| MockResult::Return("mocked 1"));
+
+        drop(guard);
+
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_guard_dropped_then_other_mocks_are_kept() {
+        mockable_2.mock_safe(|| MockResult::Return("mocked 2"));
+        let guard = mockable_1.mock_safe_scoped(|| MockResult::Return("mocked 1"));
+
+        drop(guard);
+
+        assert_eq!("not mocked 1", mockable_1());
+        assert_eq!("mocked 2", mockable_2());
+    }
+
+    #[test]
+    fn when_scope_returns_early_then_mock_is_removed() {
+        fn scope(return_early: bool) -> &'static str {
+            let _guard = mockable_1.mock_safe_scoped(|| MockResult::Return("mocked 1"));
+            if return_early {
+                return "returned early";
+            }
+            mockable_1()
+        }
+
+        assert_eq!("returned early", scope(true));
+        assert_eq!("not mocked 1", mockable_1());
+    }
+
+    #[test]
+    fn when_scope_panics_then_mock_is_removed() {
+        let result = std::panic::catch_unwind(|| {
+            let _guard = mockable_1.mock_safe_scoped(|| MockResult::Return("mocked 1"));
+            panic!("scope panic");
+        });
+
+        assert!(result.is_err());
+        assert_eq!("not mocked 1", mockable_1());
+    }
+}
+
+mod call_recorder_assert_called_with {
+    use super::*;
+
+    #[mockable]
+    fn mockable(number: u32, text: &'static str) -> String {
+        format!("{} {}", number, text)
+    }
+
+    #[test]
+    fn when_call_matches_eq_then_passes() {
+        let recorder = mockable.spy();
+
+        mockable(1, "a");
+        mockable(2, "b");
+
+        recorder.assert_called_with((args::eq(2), args::eq("b")));
+    }
+
+    #[test]
+    fn when_call_matches_any_then_passes() {
+        let recorder = mockable.spy();
+
+        mockable(1, "a");
+
+        recorder.assert_called_with((args::any(), args::any()));
+    }
+
+    #[test]
+    fn when_call_matches_predicate_then_passes() {
+        let recorder = mockable.spy();
+
+        mockable(1, "abc");
+
+        recorder.assert_called_with((
+            args::predicate(|number: &u32| *number < 2),
+            args::predicate(|text: &&str| text.starts_with('a')),
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "recorded calls: [(1, \"a\"), (2, \"b\")]")]
+    fn when_no_call_matches_then_panics_listing_calls() {
+        let recorder = mockable.spy();
+
+        mockable(1, "a");
+        mockable(2, "b");
+
+        recorder.assert_called_with((args::eq(1), args::eq("b")));
+    }
+
+    #[test]
+    #[should_panic(expected = "recorded calls: []")]
+    fn when_not_called_then_panics() {
+        let recorder = mockable.spy();
+
+        recorder.assert_called_with((args::any(), args::any()));
+    }
+}
+
+mod call_recorder_debug_snapshot {
+    use super::*;
+
+    #[mockable]
+    fn mockable(number: u32, text: &'static str) -> String {
+        format!("{} {}", number, text)
+    }
+
+    #[test]
+    fn when_called_then_lists_calls_in_order() {
+        let recorder = mockable.spy();
+
+        mockable(2, "b");
+        mockable(1, "a");
+
+        let name = std::any::type_name_of_val(&mockable);
+        let expected = format!("{0}(2, \"b\")\n{0}(1, \"a\")\n", name);
+        assert_eq!(expected, recorder.debug_snapshot());
+    }
+
+    #[test]
+    fn when_not_called_then_returns_empty_string() {
+        let recorder = mockable.spy();
+
+        assert_eq!("", recorder.debug_snapshot());
+    }
+
+    #[test]
+    fn when_spy_replaced_then_returns_empty_string() {
+        let recorder = mockable.spy();
+        mockable(1, "a");
+
+        mockable.clear_mock();
+
+        assert_eq!("", recorder.debug_snapshot());
+    }
+}
+
+mod mocking_fn_returning_reference_tied_to_arg_lifetime {
+    use super::*;
+
+    #[mockable]
+    fn first(text: &str) -> &str {
+        &text[..1]
+    }
+
+    #[mockable]
+    fn either<'a>(text_1: &'a str, _text_2: &'a str) -> &'a str {
+        text_1
+    }
+
+    #[test]
+    fn when_not_mocked_then_returns_borrowed_arg() {
+        let text = "abc".to_string();
+
+        assert_eq!("a", first(&text));
+    }
+
+    #[test]
+    fn when_mocked_returning_borrowed_arg_then_returns_it() {
+        first.mock_safe(|text| MockResult::Return(&text[1..]));
+        let text = "abc".to_string();
+
+        assert_eq!("bc", first(&text));
+    }
+
+    #[test]
+    fn when_mocked_returning_other_borrowed_arg_then_returns_it() {
+        either.mock_safe(|_, text_2| MockResult::Return(text_2));
+        let text_1 = "abc".to_string();
+        let text_2 = "def".to_string();
+
+        assert_eq!("def", either(&text_1, &text_2));
+    }
+
+    #[test]
+    fn when_mocked_returning_static_then_returns_it() {
+        first.mock_safe(|_| MockResult::Return("mocked"));
+        let text = "abc".to_string();
+
+        assert_eq!("mocked", first(&text));
+    }
+
+    #[test]
+    fn when_mocked_raw_returning_value_outliving_calls_then_returns_it() {
+        let mocked = "mocked".to_string();
+        unsafe {
+            first.mock_raw(|_| MockResult::Return(&mocked));
+        }
+        let text = "abc".to_string();
+
+        assert_eq!("mocked", first(&text));
+        clear_mocks();
+    }
+}
+
+mod mock_safe_once {
+    use super::*;
+
+    #[mockable]
+    fn mockable(arg: u32) -> String {
+        format!("not mocked {}", arg)
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock_once_and_then_runs_normally() {
+        mockable.mock_safe_once(|a| MockResult::Return(format!("mocked {}", a)));
+
+        assert_eq!("mocked 1", mockable(1));
+        assert_eq!("not mocked 2", mockable(2));
+        assert_eq!("not mocked 3", mockable(3));
+    }
+
+    #[test]
+    fn when_mock_moves_captured_value_out_then_returns_it() {
+        let mocked = "mocked".to_string();
+        mockable.mock_safe_once(move |_| MockResult::Return(mocked));
+
+        assert_eq!("mocked", mockable(1));
+    }
+
+    #[test]
+    fn when_mocked_with_continue_then_runs_once_with_modified_args() {
+        mockable.mock_safe_once(|a| MockResult::Continue((a + 10,)));
+
+        assert_eq!("not mocked 11", mockable(1));
+        assert_eq!("not mocked 2", mockable(2));
+    }
+
+    #[test]
+    fn when_mocked_then_all_calls_are_counted() {
+        mockable.mock_safe_once(|_| MockResult::Return("mocked".to_string()));
+
+        mockable(1);
+        mockable(2);
+
+        assert_eq!(2, mockable.call_count());
+    }
+
+    #[test]
+    fn when_mock_calls_mocked_function_then_it_runs_normally() {
+        mockable.mock_safe_once(|a| MockResult::Return(format!("mocked {}", mockable(a))));
+
+        assert_eq!("mocked not mocked 1", mockable(1));
+    }
+}
+
+mod set_mock_and_clear_mock_fns {
+    use super::*;
+
+    #[mockable]
+    fn mockable(arg: u32) -> String {
+        format!("not mocked {}", arg)
+    }
+
+    struct Struct;
+
+    #[mockable]
+    impl Struct {
+        fn method(&self) -> &'static str {
+            "not mocked"
+        }
+    }
+
+    #[test]
+    fn when_mocked_then_runs_mock() {
+        set_mock(mockable, |a| MockResult::Return(format!("mocked {}", a)));
+
+        assert_eq!("mocked 1", mockable(1));
+    }
+
+    #[test]
+    fn when_method_mocked_then_runs_mock() {
+        set_mock(Struct::method, |_| MockResult::Return("mocked"));
+
+        assert_eq!("mocked", Struct.method());
+    }
+
+    #[test]
+    fn when_mocked_through_alias_then_runs_mock() {
+        use self::mockable as alias;
+
+        set_mock(alias, |a| MockResult::Return(format!("mocked {}", a)));
+
+        assert_eq!("mocked 1", mockable(1));
+    }
+
+    #[test]
+    fn when_mocked_and_cleared_then_runs_normally() {
+        set_mock(mockable, |a| MockResult::Return(format!("mocked {}", a)));
+
+        clear_mock(mockable);
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+}
+
+mod panics_inside_mocks_are_annotated {
+    use super::*;
+
+    #[mockable]
+    fn mockable() -> &'static str {
+        "not mocked"
+    }
+
+    #[mockable]
+    fn mockable_other() -> &'static str {
+        "not mocked other"
+    }
+
+    trait Trait {
+        fn method(&self) -> &'static str;
+    }
+
+    struct Struct;
+
+    #[mockable]
+    impl Trait for Struct {
+        fn method(&self) -> &'static str {
+            "not mocked"
+        }
+    }
+
+    #[mockable]
+    fn panicking(arg: u32) -> &'static str {
+        panic!("real panic {}", arg)
+    }
+
+    thread_local! {
+        static PANIC_HOOK_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// Makes the panic hook count its calls in the current thread and then report panics as usual
+    fn count_panic_hook_calls() {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            let default_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                PANIC_HOOK_CALLS.with(|calls| calls.set(calls.get() + 1));
+                default_hook(info)
+            }));
+        });
+    }
+
+    fn panic_message(call: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let payload = std::panic::catch_unwind(call).unwrap_err();
+        payload
+            .downcast_ref::<String>()
+            .cloned()
+            .expect("panic payload is not a String")
+    }
+
+    #[test]
+    fn when_mock_panics_with_str_then_message_contains_fn_name() {
+        mockable.mock_safe(|| panic!("mock panic"));
+
+        let message = panic_message(|| {
+            mockable();
+        });
+
+        assert_eq!(
+            "Mocktopus: mock of 'mocking::panics_inside_mocks_are_annotated::mockable' \
+             panicked: mock panic",
+            message
+        );
+    }
+
+    #[test]
+    fn when_mock_panics_with_formatted_message_then_message_contains_fn_name() {
+        mockable.mock_safe(|| panic!("mock panic {}", 1));
+
+        let message = panic_message(|| {
+            mockable();
+        });
+
+        assert!(message.starts_with("Mocktopus: mock of '"));
+        assert!(message.ends_with("::mockable' panicked: mock panic 1"));
+    }
+
+    #[test]
+    fn when_nested_mock_panics_then_message_contains_both_fn_names() {
+        mockable.mock_safe(|| MockResult::Return(mockable_other()));
+        mockable_other.mock_safe(|| panic!("mock panic"));
+
+        let message = panic_message(|| {
+            mockable();
+        });
+
+        assert!(message.contains("::mockable' panicked: Mocktopus: mock of '"));
+        assert!(message.ends_with("::mockable_other' panicked: mock panic"));
+    }
+
+    #[test]
+    fn when_mock_panics_then_panic_hook_runs_once() {
+        count_panic_hook_calls();
+        mockable.mock_safe(|| MockResult::Return(mockable_other()));
+        mockable_other.mock_safe(|| panic!("mock panic"));
+
+        panic_message(|| {
+            mockable();
+        });
+
+        assert_eq!(1, PANIC_HOOK_CALLS.with(|calls| calls.get()));
+    }
+
+    #[test]
+    fn when_real_call_of_map_mock_panics_then_message_is_not_annotated() {
+        panicking.mock_map(|output| output);
+
+        let message = panic_message(|| {
+            panicking(1);
+        });
+
+        assert_eq!("real panic 1", message);
+    }
+
+    #[test]
+    fn when_real_call_of_non_reentrant_mock_panics_then_message_is_not_annotated() {
+        panicking.mock_safe_non_reentrant(|arg| MockResult::Continue((arg,)));
+
+        let message = panic_message(|| {
+            panicking(1);
+        });
+
+        assert_eq!("real panic 1", message);
+    }
+
+    #[test]
+    fn when_trait_method_mock_panics_then_message_contains_method_name() {
+        mock_trait_method(
+            concat!(module_path!(), "::Trait::method"),
+            || -> MockResult<(), &'static str> { panic!("mock panic") },
+        );
+
+        let message = panic_message(|| {
+            Struct.method();
+        });
+
+        assert!(message.ends_with("::<Struct as Trait>::method' panicked: mock panic"));
+    }
+
+    #[test]
+    fn when_closure_mock_panics_then_message_contains_key() {
+        mock_closure("key", || -> MockResult<(), u32> { panic!("mock panic") });
+        let closure = MockableClosure::new("key", || 1u32);
+
+        let message = panic_message(|| {
+            closure();
+        });
+
+        assert_eq!("Mocktopus: mock of 'key' panicked: mock panic", message);
+    }
+
+    #[test]
+    fn when_mock_panics_with_other_payload_then_payload_is_kept() {
+        mockable.mock_safe(|| std::panic::panic_any(7u32));
+
+        let payload = std::panic::catch_unwind(|| {
+            mockable();
+        })
+        .unwrap_err();
+
+        assert_eq!(Some(&7u32), payload.downcast_ref::<u32>());
+    }
+
+    #[test]
+    fn when_mocks_required_panic_then_message_is_not_annotated() {
+        require_mocks(true);
+
+        let payload = std::panic::catch_unwind(|| {
+            mockable();
+        })
+        .unwrap_err();
+
+        let message = payload.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("Mocktopus: mockable function '"));
+    }
+}
+
+mod is_mocked {
+    use super::*;
+
+    #[mockable]
+    fn mockable_1() -> &'static str {
+        "not mocked 1"
+    }
+
+    #[mockable]
+    fn mockable_2() -> &'static str {
+        "not mocked 2"
+    }
+
+    #[test]
+    fn when_not_mocked_then_returns_false() {
+        assert!(!mockable_1.is_mocked());
+    }
+
+    #[test]
+    fn when_mocked_then_returns_true_only_for_mocked_function() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+
+        assert!(mockable_1.is_mocked());
+        assert!(!mockable_2.is_mocked());
+    }
+
+    #[test]
+    fn when_mocked_and_cleared_then_returns_false() {
+        mockable_1.mock_safe(|| MockResult::Return("mocked 1"));
+
+        mockable_1.clear_mock();
+
+        assert!(!mockable_1.is_mocked());
+    }
+
+    #[test]
+    fn when_mocked_in_context_then_returns_true_only_while_running() {
+        MockContext::new()
+            .mock_safe(mockable_1, || MockResult::Return("mocked 1"))
+            .run(|| assert!(mockable_1.is_mocked()));
+
+        assert!(!mockable_1.is_mocked());
+    }
+
+    #[test]
+    fn when_mocked_in_other_thread_then_returns_false() {
+        std::thread::spawn(|| mockable_1.mock_safe(|| MockResult::Return("mocked 1")))
+            .join()
+            .unwrap();
+
+        assert!(!mockable_1.is_mocked());
+    }
+}
+
+mod mock_safe_boxed {
+    use super::*;
+
+    #[mockable]
+    fn mockable(arg: u32) -> u32 {
+        arg
+    }
+
+    fn build_mock(returned: Vec<Option<u32>>) -> Box<dyn FnMut(u32) -> MockResult<(u32,), u32>> {
+        let mut returned = returned.into_iter();
+        Box::new(move |arg| match returned.next().flatten() {
+            Some(value) => MockResult::Return(value),
+            None => MockResult::Continue((arg + 10,)),
+        })
+    }
+
+    #[test]
+    fn when_mock_built_at_runtime_then_it_is_used() {
+        mockable.mock_safe_boxed(build_mock(vec![Some(5), None, Some(7)]));
+
+        assert_eq!(5, mockable(1));
+        assert_eq!(12, mockable(2));
+        assert_eq!(7, mockable(3));
+        assert_eq!(14, mockable(4));
+        assert_eq!(4, mockable.call_count());
+    }
+
+    #[test]
+    fn when_boxed_fn_given_then_it_is_used() {
+        let offset = 100;
+        let mock: Box<dyn Fn(u32) -> MockResult<(u32,), u32>> =
+            Box::new(move |arg| MockResult::Return(arg + offset));
+
+        mockable.mock_safe_boxed(mock);
+
+        assert_eq!(101, mockable(1));
+    }
+
+    #[test]
+    fn when_mocked_again_then_boxed_mock_is_replaced() {
+        mockable.mock_safe_boxed(build_mock(vec![Some(5)]));
+        mockable.mock_safe(|_| MockResult::Return(6));
+
+        assert_eq!(6, mockable(1));
+    }
+
+    #[test]
+    fn when_cleared_then_boxed_mock_is_removed() {
+        mockable.mock_safe_boxed(build_mock(vec![Some(5)]));
+        mockable.clear_mock();
+
+        assert_eq!(1, mockable(1));
+    }
+}
+
+mod try_mock_safe {
+    use super::*;
+    use std::num::ParseIntError;
+
+    #[mockable]
+    fn mockable(arg: &'static str) -> u32 {
+        arg.len() as u32
+    }
+
+    #[test]
+    fn when_mock_returns_ok_return_then_returns_mocking_result() {
+        mockable.try_mock_safe(|a| Ok::<_, ParseIntError>(MockResult::Return(a.parse()?)));
+
+        assert_eq!(12, mockable("12"));
+    }
+
+    #[test]
+    fn when_mock_returns_ok_continue_then_runs_with_modified_args() {
+        mockable.try_mock_safe(|_| Ok::<_, ParseIntError>(MockResult::Continue(("abc",))));
+
+        assert_eq!(3, mockable("a"));
+    }
+
+    #[test]
+    #[should_panic(expected = "mock returned error: invalid digit found in string")]
+    fn when_mock_returns_err_then_panics_with_error_message() {
+        mockable.try_mock_safe(|a| Ok::<_, ParseIntError>(MockResult::Return(a.parse()?)));
+
+        mockable("abc");
+    }
+}
+
+mod mock_safe_non_reentrant {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[mockable]
+    fn factorial(n: u64) -> u64 {
+        if n <= 1 {
+            1
+        } else {
+            n * factorial(n - 1)
+        }
+    }
+
+    #[test]
+    fn when_mock_continues_then_recursive_calls_run_normally() {
+        factorial.mock_safe_non_reentrant(|n| MockResult::Continue((n + 1,)));
+
+        assert_eq!(24, factorial(3));
+    }
+
+    #[test]
+    fn when_mock_continues_then_it_runs_once_per_outermost_call() {
+        let mock_calls = Rc::new(RefCell::new(Vec::new()));
+        let mock_calls_clone = mock_calls.clone();
+        factorial.mock_safe_non_reentrant(move |n| {
+            mock_calls_clone.borrow_mut().push(n);
+            MockResult::Continue((n,))
+        });
+
+        assert_eq!(120, factorial(5));
+        assert_eq!(6, factorial(3));
+        assert_eq!(vec![5, 3], *mock_calls.borrow());
+    }
+
+    #[test]
+    fn when_mock_returns_then_returns_mocking_result() {
+        factorial.mock_safe_non_reentrant(|n| MockResult::Return(n * 10));
+
+        assert_eq!(50, factorial(5));
+    }
+
+    #[test]
+    fn when_mock_panics_then_next_outermost_call_is_mocked() {
+        factorial.mock_safe_non_reentrant(|n| {
+            if n == 1 {
+                panic!("mock panicked");
+            }
+            MockResult::Return(0)
+        });
+
+        assert!(std::panic::catch_unwind(|| factorial(1)).is_err());
+        assert_eq!(0, factorial(5));
+    }
+}
+
+mod mock_map {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        name: String,
+        retries: u32,
+    }
+
+    #[mockable]
+    fn load_config(name: &str) -> Config {
+        Config {
+            name: name.to_string(),
+            retries: 3,
+        }
+    }
+
+    #[mockable]
+    fn factorial(n: u64) -> u64 {
+        if n <= 1 {
+            1
+        } else {
+            n * factorial(n - 1)
+        }
+    }
+
+    #[test]
+    fn when_mocked_then_returns_transformed_real_result() {
+        load_config.mock_map(|config| Config {
+            retries: 0,
+            ..config
+        });
+
+        assert_eq!(
+            Config {
+                name: "real".to_string(),
+                retries: 0
+            },
+            load_config("real")
+        );
+        assert_eq!(1, load_config.call_count());
+    }
+
+    #[test]
+    fn when_mocked_recursive_function_then_transforms_only_outermost_result() {
+        factorial.mock_map(|result| result + 1);
+
+        assert_eq!(7, factorial(3));
+        assert_eq!(3, factorial.call_count());
+    }
+
+    #[test]
+    fn when_cleared_then_runs_normally() {
+        factorial.mock_map(|result| result + 1);
+
+        factorial.clear_mock();
+
+        assert_eq!(6, factorial(3));
+    }
+}
+
+mod mock_map_args {
+    use super::*;
+
+    #[mockable]
+    fn square_root(x: i32) -> i32 {
+        assert!(x >= 0, "negative argument");
+        (x as f64).sqrt() as i32
+    }
+
+    #[mockable]
+    fn append(text: &mut String, suffix: &str) {
+        text.push_str(suffix);
+    }
+
+    #[mockable]
+    fn countdown(n: u32) -> u32 {
+        if n == 0 {
+            0
+        } else {
+            1 + countdown(n - 1)
+        }
+    }
+
+    #[test]
+    fn when_mocked_then_runs_with_clamped_args() {
+        square_root.mock_map_args(|x| (x.max(0),));
+
+        assert_eq!(0, square_root(-4));
+        assert_eq!(3, square_root(9));
+        assert_eq!(2, square_root.call_count());
+    }
+
+    #[test]
+    fn when_mocked_then_modified_mut_ref_args_stay_modified() {
+        append.mock_map_args(|text, suffix| {
+            text.push('>');
+            (text, suffix)
+        });
+        let mut text = "a".to_string();
+
+        append(&mut text, "b");
+
+        assert_eq!("a>b", text);
+    }
+
+    #[test]
+    fn when_mocked_recursive_function_then_maps_args_of_all_calls() {
+        countdown.mock_map_args(|n| (n.saturating_sub(1),));
+
+        assert_eq!(2, countdown(4));
+        assert_eq!(3, countdown.call_count());
+    }
+}
+
+mod mock_delegate_to {
+    use super::*;
+
+    #[mockable]
+    fn old_api(a: u32, b: &str) -> String {
+        format!("old {} {}", a, b)
+    }
+
+    #[mockable]
+    fn new_api(a: u32, b: &str) -> String {
+        format!("new {} {}", a, b)
+    }
+
+    #[test]
+    fn when_mocked_then_forwards_calls_to_delegate() {
+        old_api.mock_delegate_to(new_api);
+
+        assert_eq!("new 1 a", old_api(1, "a"));
+        assert_eq!(1, old_api.call_count());
+        assert_eq!(1, new_api.call_count());
+    }
+
+    #[test]
+    fn when_delegate_mocked_then_its_mock_is_used() {
+        old_api.mock_delegate_to(new_api);
+        new_api.mock_safe(|a, _| MockResult::Return(format!("mocked {}", a)));
+
+        assert_eq!("mocked 1", old_api(1, "a"));
+    }
+
+    #[test]
+    fn when_delegate_is_closure_then_forwards_calls_to_it() {
+        let mut calls = 0;
+        old_api.mock_delegate_to(move |a, b: &str| {
+            calls += 1;
+            format!("closure {} {} {}", a, b, calls)
+        });
+
+        assert_eq!("closure 1 a 1", old_api(1, "a"));
+        assert_eq!("closure 2 b 2", old_api(2, "b"));
+    }
+}
+
+mod mock_builder {
+    use super::*;
+
+    #[mockable]
+    fn mockable(arg: u32) -> String {
+        format!("not mocked {}", arg)
+    }
+
+    #[test]
+    fn when_installed_then_first_matching_branch_is_used() {
+        mockable
+            .mock_builder()
+            .when(|&(a,)| a == 1)
+            .then(|_| MockResult::Return("mocked 1".to_string()))
+            .when(|&(a,)| a < 3)
+            .then(|_| MockResult::Return("mocked 2".to_string()))
+            .install();
+
+        assert_eq!("mocked 1", mockable(1));
+        assert_eq!("mocked 2", mockable(2));
+        assert_eq!("not mocked 3", mockable(3));
+    }
+
+    #[test]
+    fn when_branch_continues_then_runs_with_modified_args() {
+        mockable
+            .mock_builder()
+            .when(|&(a,)| a == 1)
+            .then(|a| MockResult::Continue((a + 10,)))
+            .install();
+
+        assert_eq!("not mocked 11", mockable(1));
+        assert_eq!("not mocked 2", mockable(2));
+    }
+
+    #[test]
+    fn when_not_installed_then_runs_normally() {
+        let _builder = mockable
+            .mock_builder()
+            .when(|&(a,)| a == 1)
+            .then(|_| MockResult::Return("mocked".to_string()));
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+
+    #[test]
+    fn when_installed_then_replaces_previous_mock() {
+        mockable.mock_safe(|_| MockResult::Return("mocked".to_string()));
+
+        mockable
+            .mock_builder()
+            .when(|&(a,)| a == 1)
+            .then(|_| MockResult::Return("mocked 1".to_string()))
+            .install();
+
+        assert_eq!("mocked 1", mockable(1));
+        assert_eq!("not mocked 2", mockable(2));
+    }
+
+    #[test]
+    fn when_installed_without_branches_then_runs_normally() {
+        mockable.mock_safe(|_| MockResult::Return("mocked".to_string()));
+
+        mockable.mock_builder().install();
+
+        assert_eq!("not mocked 1", mockable(1));
+    }
+}
+
+mod mock_result_panic {
+    use super::*;
+    use std::panic::catch_unwind;
+
+    #[mockable]
+    fn mockable(arg: String) -> String {
+        arg
+    }
+
+    #[test]
+    #[should_panic(expected = "dependency failed")]
+    fn when_mock_returns_panic_then_panics() {
+        mockable.mock_safe(|_| MockResult::Panic("dependency failed".to_string()));
+
+        mockable("not mocked".to_string());
+    }
+
+    #[test]
+    fn when_mock_returns_panic_then_panic_payload_is_message() {
+        mockable.mock_safe(|_| MockResult::Panic("dependency failed".to_string()));
+
+        let payload = catch_unwind(|| mockable("not mocked".to_string())).unwrap_err();
+
+        assert_eq!(
+            Some(&"dependency failed".to_string()),
+            payload.downcast_ref::<String>()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "closure failed")]
+    fn when_closure_mock_returns_panic_then_panics() {
+        let closure = MockableClosure::new("mock_result_panic", |a: u32| a);
+        mock_closure("mock_result_panic", |_: u32| {
+            MockResult::<_, u32>::Panic("closure failed".to_string())
+        });
+
+        closure(1);
+    }
+}
+
+mod inspect {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[mockable]
+    fn mockable(name: String, suffix: Vec<String>) -> String {
+        format!("{} {}", name, suffix.join(" "))
+    }
+
+    #[test]
+    fn when_inspected_then_inspector_observes_args_and_function_runs_normally() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let log_clone = log.clone();
+        mockable.inspect(move |(name, suffix)| {
+            log_clone
+                .borrow_mut()
+                .push(format!("{} {}", name, suffix.len()))
+        });
+
+        assert_eq!(
+            "a b c",
+            mockable("a".to_string(), vec!["b".to_string(), "c".to_string()])
+        );
+        assert_eq!("d ", mockable("d".to_string(), vec![]));
+        assert_eq!(vec!["a 2", "d 0"], *log.borrow());
+    }
+
+    #[test]
+    fn when_mock_reads_owned_args_and_continues_then_function_receives_them() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let log_clone = log.clone();
+        mockable.mock_safe(move |name, suffix| {
+            log_clone.borrow_mut().push(name.clone());
+            MockResult::Continue((name, suffix))
+        });
+
+        assert_eq!("a b", mockable("a".to_string(), vec!["b".to_string()]));
+        assert_eq!(vec!["a"], *log.borrow());
+    }
+
+    #[test]
+    fn when_inspected_then_calls_are_counted() {
+        mockable.inspect(|_| ());
+
+        mockable("a".to_string(), vec![]);
+        mockable("b".to_string(), vec![]);
+
+        assert_eq!(2, mockable.call_count());
+    }
+}
+
+mod verify_macro {
+    use super::*;
+
+    #[mockable]
+    fn mockable() {}
+
+    #[test]
+    fn when_call_count_matches_then_passes() {
+        mockable();
+        mockable();
+
+        verify!(mockable, times = 2);
+        verify!(mockable, at_least = 2);
+        verify!(mockable, at_least = 1);
+        verify!(mockable, at_most = 2);
+        verify!(mockable, at_most = 3);
+    }
+
+    #[test]
+    fn when_not_called_then_never_passes() {
+        verify!(mockable, never);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mocktopus: expected 'mockable' to be called exactly 1 times, but it was called 2 times"
+    )]
+    fn when_called_more_times_then_times_panics() {
+        mockable();
+        mockable();
+
+        verify!(mockable, times = 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mocktopus: expected 'mockable' to be called at least 2 times, but it was called 1 times"
+    )]
+    fn when_called_less_times_then_at_least_panics() {
+        mockable();
+
+        verify!(mockable, at_least = 2);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mocktopus: expected 'mockable' to be called at most 1 times, but it was called 2 times"
+    )]
+    fn when_called_more_times_then_at_most_panics() {
+        mockable();
+        mockable();
+
+        verify!(mockable, at_most = 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mocktopus: expected 'mockable' to be called exactly 0 times, but it was called 1 times"
+    )]
+    fn when_called_then_never_panics() {
+        mockable();
+
+        verify!(mockable, never);
+    }
+}
+
+mod mocked_test_macro {
+    use super::*;
+
+    #[mockable]
+    fn first() -> u32 {
+        1
+    }
+
+    #[mockable]
+    fn second(arg: u32) -> u32 {
+        arg * 2
+    }
+
+    fn caller() -> u32 {
+        first() + second(10)
+    }
+
+    #[test]
+    fn when_body_runs_then_mock_is_used_and_cleared_afterwards() {
+        mocked_test!(first => || MockResult::Return(5), {
+            assert_eq!(25, caller());
+        });
+
+        assert_eq!(21, caller());
+    }
+
+    #[test]
+    fn when_multiple_mocks_given_then_all_are_used_and_cleared_afterwards() {
+        mocked_test!(
+            first => || MockResult::Return(5),
+            second => |arg| MockResult::Continue((arg + 1,)),
+            {
+                assert_eq!(27, caller());
+                verify!(second, times = 1);
+            }
+        );
+
+        assert_eq!(21, caller());
+    }
+
+    #[test]
+    fn when_body_evaluates_to_value_then_returns_it() {
+        let result = mocked_test!(second => |_| MockResult::Return(7), { caller() });
+
+        assert_eq!(8, result);
+    }
+
+    #[test]
+    fn when_body_panics_then_mocks_are_cleared() {
+        let result = std::panic::catch_unwind(|| {
+            mocked_test!(first => || MockResult::Return(5), {
+                panic!("body panicked");
+            })
+        });
+
+        assert!(result.is_err());
+        assert_eq!(21, caller());
+    }
+}
+
+mod mock_safe_returning_owned_captured_data {
+    use super::*;
+
+    #[mockable]
+    fn mockable() -> Vec<u8> {
+        vec![0]
+    }
+
+    #[test]
+    fn when_mock_clones_captured_data_then_every_call_gets_independent_value() {
+        let data = vec![1, 2, 3];
+        mockable.mock_safe(move || MockResult::Return(data.clone()));
+
+        let mut first = mockable();
+        first.push(4);
+        let second = mockable();
+
+        assert_eq!(vec![1, 2, 3, 4], first);
+        assert_eq!(vec![1, 2, 3], second);
+        assert_eq!(vec![1, 2, 3], mockable());
+    }
+
+    #[test]
+    fn when_mock_modifies_captured_data_then_every_call_gets_its_own_snapshot() {
+        let mut data = Vec::new();
+        mockable.mock_safe(move || {
+            data.push(data.len() as u8);
+            MockResult::Return(data.clone())
+        });
+
+        assert_eq!(vec![0], mockable());
+        assert_eq!(vec![0, 1], mockable());
+        assert_eq!(vec![0, 1, 2], mockable());
     }
 }
@@ -0,0 +1,91 @@
+#![cfg(feature = "enabled")]
+#![feature(custom_inner_attributes, prelude_import, proc_macro_hygiene)]
+#![allow(internal_features)]
+#![mocktopus::macros::mockable_crate]
+
+use mocktopus::macros::*;
+use mocktopus::mocking::*;
+
+mod mocking_crate_file_module;
+
+fn function() -> &'static str {
+    "not mocked"
+}
+
+#[not_mockable]
+fn not_mockable_function() -> &'static str {
+    "not mocked"
+}
+
+mod module {
+    use mocktopus::macros::*;
+
+    pub fn function() -> &'static str {
+        "not mocked"
+    }
+
+    pub struct Struct;
+
+    impl Struct {
+        pub fn method(&self) -> &'static str {
+            "not mocked"
+        }
+    }
+
+    pub trait Trait {
+        fn method(&self) -> &'static str {
+            "not mocked"
+        }
+    }
+
+    impl Trait for Struct {}
+
+    #[not_mockable]
+    pub mod not_mockable_module {
+        pub fn function() -> &'static str {
+            "not mocked"
+        }
+    }
+}
+
+#[test]
+fn when_not_mocked_then_runs_normally() {
+    assert_eq!("not mocked", function());
+    assert_eq!("not mocked", module::function());
+    assert_eq!("not mocked", module::Struct.method());
+    assert_eq!("not mocked", module::Trait::method(&module::Struct));
+}
+
+#[test]
+fn when_fn_in_crate_root_mocked_then_runs_mock() {
+    function.mock_safe(|| MockResult::Return("mocked"));
+
+    assert_eq!("mocked", function());
+}
+
+#[test]
+fn when_items_in_inline_module_mocked_then_run_mocks() {
+    module::function.mock_safe(|| MockResult::Return("mocked fn"));
+    module::Struct::method.mock_safe(|_| MockResult::Return("mocked method"));
+    <module::Struct as module::Trait>::method.mock_safe(|_| MockResult::Return("mocked trait"));
+
+    assert_eq!("mocked fn", module::function());
+    assert_eq!("mocked method", module::Struct.method());
+    assert_eq!("mocked trait", module::Trait::method(&module::Struct));
+}
+
+#[test]
+fn when_not_mockable_items_mocked_then_run_normally() {
+    not_mockable_function.mock_safe(|| MockResult::Return("mocked"));
+    module::not_mockable_module::function.mock_safe(|| MockResult::Return("mocked"));
+
+    assert_eq!("not mocked", not_mockable_function());
+    assert_eq!("not mocked", module::not_mockable_module::function());
+}
+
+#[test]
+fn when_fn_in_file_module_with_inner_attribute_mocked_then_runs_mock() {
+    mocking_crate_file_module::function.mock_safe(|| MockResult::Return("mocked"));
+
+    assert_eq!("mocked", mocking_crate_file_module::function());
+}
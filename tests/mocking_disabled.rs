@@ -0,0 +1,40 @@
+#![cfg(not(feature = "enabled"))]
+// Run with `cargo test -p mocktopus --no-default-features`, like in CI
+
+use mocktopus::macros::*;
+use mocktopus::mocking::*;
+
+#[mockable]
+fn function(arg: u32) -> u32 {
+    arg * 2
+}
+
+struct Struct;
+
+#[mockable]
+impl Struct {
+    fn method(&self, arg: u32) -> u32 {
+        arg * 3
+    }
+}
+
+#[test]
+fn when_mocked_then_fn_runs_normally() {
+    function.mock_safe(|_| MockResult::Return(0));
+
+    assert_eq!(4, function(2));
+}
+
+#[test]
+fn when_mocked_then_method_runs_normally() {
+    Struct::method.mock_safe(|_, _| MockResult::Return(0));
+
+    assert_eq!(6, Struct.method(2));
+}
+
+#[test]
+fn when_called_then_call_is_not_counted() {
+    function(2);
+
+    assert_eq!(0, function.call_count());
+}
@@ -1,6 +1,23 @@
 use super::*;
 
+mod when_fn_closure_arg;
+mod when_fn_const_generic;
+mod when_fn_diverging;
+mod when_fn_feature_gated;
 mod when_fn_generic;
 mod when_fn_generic_async;
+mod when_fn_generic_bounded;
+mod when_fn_ignored_args;
+mod when_fn_inline;
+mod when_fn_must_use;
+mod when_fn_mut_ref_args;
+mod when_fn_nested;
+mod when_fn_raw_pointer_args;
 mod when_fn_regular;
 mod when_fn_regular_async;
+mod when_fn_returning_aggregate;
+mod when_fn_returning_impl_trait;
+mod when_fn_returning_primitive;
+mod when_fn_slice_args;
+mod when_fn_track_caller;
+mod when_fn_zero_sized_types;
@@ -0,0 +1,59 @@
+use super::*;
+use std::cell::Cell;
+
+#[mockable]
+fn retry<F: Fn() -> bool>(attempts: u32, f: F) -> bool {
+    (0..attempts).any(|_| f())
+}
+
+fn mock_retry_for<F: Fn() -> bool + 'static>(_: &F, result: bool) {
+    retry::<F>.mock_safe(move |_, _| MockResult::Return(result));
+}
+
+fn invoke_forbidden() -> bool {
+    panic!("closure invoked")
+}
+
+#[test]
+fn and_not_mocked_then_invokes_closure() {
+    let calls = Cell::new(0);
+
+    assert!(!retry(3, || {
+        calls.set(calls.get() + 1);
+        false
+    }));
+    assert_eq!(3, calls.get());
+}
+
+#[test]
+fn and_mocked_for_closure_type_then_closure_is_never_invoked() {
+    let closure = || -> bool { panic!("closure invoked") };
+    mock_retry_for(&closure, true);
+
+    assert!(retry(3, closure));
+}
+
+#[test]
+fn and_mocked_for_closure_type_then_other_closures_run_normally() {
+    let mocked = || -> bool { panic!("closure invoked") };
+    let other = || true;
+    mock_retry_for(&mocked, false);
+
+    assert!(!retry(3, mocked));
+    assert!(retry(3, other));
+}
+
+#[test]
+fn and_mocked_for_fn_pointer_then_fn_pointer_is_never_invoked() {
+    retry::<fn() -> bool>.mock_safe(|_, _| MockResult::Return(true));
+
+    assert!(retry(3, invoke_forbidden as fn() -> bool));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    let closure = || true;
+    retry::<fn() -> bool>.mock_safe(|_, f| MockResult::Continue((0, f)));
+
+    assert!(!retry(3, closure as fn() -> bool));
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+#[mockable]
+fn function<const N: usize>(arg: u32) -> String {
+    format!("{} {}", arg, N)
+}
+
+#[mockable]
+fn mixed<T: Display, const N: usize>(arg: T) -> String {
+    format!("{} {}", arg, N)
+}
+
+#[mockable]
+fn buffer<const N: usize>() -> [u8; N] {
+    [0; N]
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!("1 2", function::<2>(1));
+    assert_eq!("a 2", mixed::<_, 2>("a"));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args_for_mocked_const_only() {
+    function::<2>.mock_safe(|a| MockResult::Continue((a + 1,)));
+    mixed::<&str, 2>.mock_safe(|_| MockResult::Continue(("b",)));
+
+    assert_eq!("2 2", function::<2>(1));
+    assert_eq!("1 3", function::<3>(1));
+    assert_eq!("b 2", mixed::<_, 2>("a"));
+    assert_eq!("a 3", mixed::<_, 3>("a"));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result_for_mocked_const_only() {
+    function::<2>.mock_safe(|a| MockResult::Return(format!("mocked {}", a)));
+    mixed::<&str, 2>.mock_safe(|a| MockResult::Return(format!("mocked {}", a)));
+
+    assert_eq!("mocked 1", function::<2>(1));
+    assert_eq!("1 3", function::<3>(1));
+    assert_eq!("mocked a", mixed::<_, 2>("a"));
+    assert_eq!("a 3", mixed::<_, 3>("a"));
+}
+
+#[test]
+fn and_returning_const_sized_array_mocked_then_returns_mocking_result_for_each_const() {
+    buffer::<2>.mock_safe(|| MockResult::Return([1, 2]));
+    buffer::<3>.mock_safe(|| MockResult::Return([3, 4, 5]));
+
+    assert_eq!([1, 2], buffer::<2>());
+    assert_eq!([3, 4, 5], buffer::<3>());
+    assert_eq!([0; 4], buffer::<4>());
+}
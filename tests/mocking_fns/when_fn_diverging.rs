@@ -0,0 +1,28 @@
+use super::*;
+
+#[mockable]
+fn diverging(message: &str) -> ! {
+    panic!("not mocked {}", message)
+}
+
+#[test]
+#[should_panic(expected = "not mocked abc")]
+fn and_not_mocked_then_runs_normally() {
+    diverging("abc");
+}
+
+#[test]
+#[should_panic(expected = "not mocked def")]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    diverging.mock_safe(|_| MockResult::Continue(("def",)));
+
+    diverging("abc");
+}
+
+#[test]
+#[should_panic(expected = "mocked abc")]
+fn and_panic_mocked_then_panics_with_mocked_message() {
+    diverging.mock_safe(|message| MockResult::Panic(format!("mocked {}", message)));
+
+    diverging("abc");
+}
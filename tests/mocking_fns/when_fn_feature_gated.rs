@@ -0,0 +1,72 @@
+use super::*;
+
+// Injected items keep their `cfg` attributes, so items gated off are removed before they are
+// compiled. The gated off variants use undefined items and would fail to compile otherwise.
+
+#[cfg(feature = "enabled")]
+#[mockable]
+mod gated_module {
+    pub fn get() -> u32 {
+        1
+    }
+
+    #[cfg(not(feature = "enabled"))]
+    pub fn get_gated_off() -> u32 {
+        undefined_fn()
+    }
+}
+
+#[cfg(not(feature = "enabled"))]
+#[mockable]
+mod gated_module {
+    pub fn get() -> UndefinedType {
+        undefined_fn()
+    }
+}
+
+#[mockable]
+#[cfg(feature = "enabled")]
+fn gated_fn() -> u32 {
+    1
+}
+
+#[mockable]
+#[cfg(not(feature = "enabled"))]
+fn gated_fn() -> UndefinedType {
+    undefined_fn()
+}
+
+#[mockable]
+mod module_with_gated_items {
+    #[cfg(feature = "enabled")]
+    pub mod inner {
+        pub fn get() -> u32 {
+            1
+        }
+    }
+
+    #[cfg(not(feature = "enabled"))]
+    pub mod inner {
+        pub fn get() -> UndefinedType {
+            undefined_fn()
+        }
+    }
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!(1, gated_module::get());
+    assert_eq!(1, gated_fn());
+    assert_eq!(1, module_with_gated_items::inner::get());
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    gated_module::get.mock_safe(|| MockResult::Return(2));
+    gated_fn.mock_safe(|| MockResult::Return(3));
+    module_with_gated_items::inner::get.mock_safe(|| MockResult::Return(4));
+
+    assert_eq!(2, gated_module::get());
+    assert_eq!(3, gated_fn());
+    assert_eq!(4, module_with_gated_items::inner::get());
+}
@@ -1,4 +1,5 @@
 use super::*;
+use std::str::FromStr;
 
 #[mockable]
 fn function<T: Display>(arg: bool, fn_generic: T) -> String {
@@ -30,3 +31,44 @@ fn and_return_mocked_then_returns_mocking_result_for_mocked_type_only() {
     assert_eq!("mocked true 2.5", function(true, 2.5f32));
     assert_eq!("true abc", function(true, "abc"));
 }
+
+#[mockable]
+fn parse<T: FromStr>(string: &str) -> Option<T> {
+    string.parse().ok()
+}
+
+#[test]
+fn and_generic_only_in_returned_type_return_mocked_then_each_variant_returns_its_mocking_result() {
+    parse::<i32>.mock_safe(|_| MockResult::Return(Some(1)));
+    parse::<f64>.mock_safe(|_| MockResult::Return(Some(2.5)));
+
+    assert_eq!(Some(1), parse::<i32>("abc"));
+    assert_eq!(Some(2.5), parse::<f64>("abc"));
+    assert_eq!(Some(3u8), parse::<u8>("3"));
+}
+
+#[mockable]
+fn make<T: Default>() -> T {
+    T::default()
+}
+
+#[test]
+fn and_generic_without_args_return_mocked_then_returns_mocking_result_for_mocked_variant_only() {
+    make::<String>.mock_safe(|| MockResult::Return("mocked".to_string()));
+
+    assert_eq!("mocked", make::<String>());
+    assert_eq!(0, make::<i32>());
+    assert_eq!(Vec::<String>::new(), make::<Vec<String>>());
+}
+
+#[test]
+fn and_generic_without_args_return_mocked_for_many_variants_then_each_returns_its_mocking_result() {
+    make::<String>.mock_safe(|| MockResult::Return("mocked".to_string()));
+    make::<i32>.mock_safe(|| MockResult::Return(1));
+    make::<Vec<String>>.mock_safe(|| MockResult::Return(vec!["mocked".to_string()]));
+
+    assert_eq!("mocked", make::<String>());
+    assert_eq!(1, make::<i32>());
+    assert_eq!(vec!["mocked".to_string()], make::<Vec<String>>());
+    assert_eq!(0u8, make::<u8>());
+}
@@ -32,3 +32,12 @@ async fn and_return_mocked_then_returns_mocking_result_for_mocked_type_only() {
     assert_eq!("mocked true 2.5", function(true, 2.5f32).await);
     assert_eq!("true abc", function(true, "abc").await);
 }
+
+#[tokio::test]
+async fn and_return_mocked_with_ready_future_then_returns_mocking_result_for_mocked_type_only() {
+    function::<f32>
+        .mock_safe(|a, b| MockResult::Return(ready_future(format!("mocked {} {}", a, b))));
+
+    assert_eq!("mocked true 2.5", function(true, 2.5f32).await);
+    assert_eq!("true abc", function(true, "abc").await);
+}
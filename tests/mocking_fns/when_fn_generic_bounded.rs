@@ -0,0 +1,45 @@
+use super::*;
+
+#[mockable]
+fn function<T>(arg: T) -> String
+where
+    T: Display + Clone + 'static,
+{
+    format!("{} {}", arg.clone(), arg)
+}
+
+#[mockable]
+fn with_lifetime<'a, 'b: 'a, T: Display + 'a>(arg: &'a T, other: &'b str) -> String
+where
+    T: ?Sized,
+{
+    format!("{} {}", arg, other)
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!("1 1", function(1));
+    assert_eq!("a b", with_lifetime("a", "b"));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args_for_mocked_type_only() {
+    function::<u32>.mock_safe(|a| MockResult::Continue((a + 1,)));
+    with_lifetime::<str>.mock_safe(|_, b| MockResult::Continue(("c", b)));
+
+    assert_eq!("2 2", function(1u32));
+    assert_eq!("a a", function("a"));
+    assert_eq!("c b", with_lifetime("a", "b"));
+    assert_eq!("1 b", with_lifetime(&1, "b"));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result_for_mocked_type_only() {
+    function::<u32>.mock_safe(|a| MockResult::Return(format!("mocked {}", a)));
+    with_lifetime::<str>.mock_safe(|a, b| MockResult::Return(format!("mocked {} {}", a, b)));
+
+    assert_eq!("mocked 1", function(1u32));
+    assert_eq!("a a", function("a"));
+    assert_eq!("mocked a b", with_lifetime("a", "b"));
+    assert_eq!("1 b", with_lifetime(&1, "b"));
+}
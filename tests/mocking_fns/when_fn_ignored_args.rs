@@ -0,0 +1,26 @@
+use super::*;
+
+#[mockable]
+fn function(value: u32, _prefixed: u32, _: u32, _: &str) -> u32 {
+    value
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!(1, function(1, 2, 3, "a"));
+}
+
+#[test]
+fn and_return_mocked_then_mock_receives_all_args_in_order() {
+    function
+        .mock_safe(|a, b, c, d| MockResult::Return(a * 1000 + b * 100 + c * 10 + d.len() as u32));
+
+    assert_eq!(1234, function(1, 2, 3, "abcd"));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    function.mock_safe(|a, b, c, d| MockResult::Continue((c, a, b, d)));
+
+    assert_eq!(3, function(1, 2, 3, "a"));
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+#[mockable]
+#[inline]
+fn inline(arg: u32) -> u32 {
+    arg
+}
+
+#[mockable]
+#[inline(always)]
+fn inline_always(arg: u32) -> u32 {
+    arg
+}
+
+#[mockable]
+#[cold]
+fn cold(arg: u32) -> u32 {
+    arg
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!(1, inline(1));
+    assert_eq!(1, inline_always(1));
+    assert_eq!(1, cold(1));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    inline.mock_safe(|a| MockResult::Continue((a + 1,)));
+    inline_always.mock_safe(|a| MockResult::Continue((a + 1,)));
+    cold.mock_safe(|a| MockResult::Continue((a + 1,)));
+
+    assert_eq!(2, inline(1));
+    assert_eq!(2, inline_always(1));
+    assert_eq!(2, cold(1));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    inline.mock_safe(|a| MockResult::Return(a + 2));
+    inline_always.mock_safe(|a| MockResult::Return(a + 2));
+    cold.mock_safe(|a| MockResult::Return(a + 2));
+
+    assert_eq!(3, inline(1));
+    assert_eq!(3, inline_always(1));
+    assert_eq!(3, cold(1));
+}
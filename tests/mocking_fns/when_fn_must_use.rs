@@ -0,0 +1,32 @@
+use super::*;
+
+// Injected functions keep the attribute, so ignoring their results still triggers `unused_must_use` lint
+#[must_use]
+#[derive(Debug, PartialEq)]
+struct Status(u32);
+
+#[mockable]
+#[must_use]
+fn compute(arg: u32) -> u32 {
+    arg
+}
+
+#[mockable]
+fn status(arg: u32) -> Status {
+    Status(arg)
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!(1, compute(1));
+    assert_eq!(Status(1), status(1));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    compute.mock_safe(|a| MockResult::Return(a + 1));
+    status.mock_safe(|a| MockResult::Return(Status(a + 1)));
+
+    assert_eq!(2, compute(1));
+    assert_eq!(Status(2), status(1));
+}
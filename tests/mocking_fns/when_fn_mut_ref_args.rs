@@ -0,0 +1,75 @@
+use super::*;
+
+#[mockable]
+fn double(value: &mut i32) -> i32 {
+    *value *= 2;
+    *value
+}
+
+#[mockable]
+fn add_to_both(first: &mut i32, second: &mut i32, amount: i32) -> i32 {
+    *first += amount;
+    *second += amount;
+    *first + *second
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    let mut value = 2;
+
+    assert_eq!(4, double(&mut value));
+    assert_eq!(4, value);
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_value_incremented_by_mock() {
+    double.mock_safe(|value| {
+        *value += 1;
+        MockResult::Continue((value,))
+    });
+    let mut value = 2;
+
+    assert_eq!(6, double(&mut value));
+    assert_eq!(6, value);
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_all_values_modified_by_mock() {
+    add_to_both.mock_safe(|first, second, amount| {
+        *first += 1;
+        *second *= 10;
+        MockResult::Continue((first, second, amount + 1))
+    });
+    let mut first = 1;
+    let mut second = 2;
+
+    assert_eq!(26, add_to_both(&mut first, &mut second, 1));
+    assert_eq!(4, first);
+    assert_eq!(22, second);
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_references_swapped_by_mock() {
+    add_to_both.mock_safe(|first, second, amount| {
+        *first += 1;
+        MockResult::Continue((second, first, amount))
+    });
+    let mut first = 1;
+    let mut second = 10;
+
+    assert_eq!(14, add_to_both(&mut first, &mut second, 1));
+    assert_eq!(3, first);
+    assert_eq!(11, second);
+}
+
+#[test]
+fn and_return_mocked_then_keeps_value_modified_by_mock() {
+    double.mock_safe(|value| {
+        *value += 1;
+        MockResult::Return(0)
+    });
+    let mut value = 2;
+
+    assert_eq!(0, double(&mut value));
+    assert_eq!(3, value);
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+// Nested items can't be named outside of their enclosing fn, so it hands the item out
+fn nested_helper() -> impl Fn(u32) -> u32 + Copy {
+    #[mockable]
+    fn helper(x: u32) -> u32 {
+        x * 2
+    }
+
+    helper
+}
+
+fn outer(x: u32) -> u32 {
+    nested_helper()(x) + 1
+}
+
+fn outer_mocking_nested(x: u32) -> u32 {
+    #[mockable]
+    fn helper(x: u32) -> u32 {
+        x * 2
+    }
+
+    helper.mock_safe(|x| MockResult::Return(x * 10));
+    helper(x) + 1
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!(5, outer(2));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    nested_helper().mock_safe(|x| MockResult::Return(x * 10));
+
+    assert_eq!(21, outer(2));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    nested_helper().mock_safe(|x| MockResult::Continue((x + 1,)));
+
+    assert_eq!(7, outer(2));
+    assert_eq!(1, nested_helper().call_count());
+}
+
+#[test]
+fn and_mocked_inside_enclosing_fn_then_returns_mocking_result() {
+    assert_eq!(21, outer_mocking_nested(2));
+}
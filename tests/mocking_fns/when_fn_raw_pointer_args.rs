@@ -0,0 +1,71 @@
+use super::*;
+
+#[mockable]
+fn increment(ptr: *mut u8) -> u8 {
+    unsafe {
+        *ptr += 1;
+        *ptr
+    }
+}
+
+#[mockable]
+fn first(ptr: *const u8, len: usize) -> Option<u8> {
+    if len == 0 {
+        None
+    } else {
+        Some(unsafe { *ptr })
+    }
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    let mut value = 1u8;
+
+    assert_eq!(2, increment(&mut value));
+    assert_eq!(2, value);
+}
+
+#[test]
+fn and_return_mocked_then_mock_inspects_pointer() {
+    let mut value = 1u8;
+    let expected = &mut value as *mut u8;
+    increment.mock_safe(move |ptr| {
+        assert_eq!(expected, ptr);
+        MockResult::Return(unsafe { *ptr } + 10)
+    });
+
+    assert_eq!(11, increment(&mut value));
+    assert_eq!(1, value);
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_pointee_modified_by_mock() {
+    increment.mock_safe(|ptr| {
+        unsafe { *ptr += 10 };
+        MockResult::Continue((ptr,))
+    });
+    let mut value = 1u8;
+
+    assert_eq!(12, increment(&mut value));
+    assert_eq!(12, value);
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_pointer_replaced_by_mock() {
+    let other = Box::into_raw(Box::new(5u8));
+    increment.mock_safe(move |_| MockResult::Continue((other,)));
+    let mut value = 1u8;
+
+    assert_eq!(6, increment(&mut value));
+    assert_eq!(1, value);
+    assert_eq!(6, *unsafe { Box::from_raw(other) });
+}
+
+#[test]
+fn and_const_pointer_continue_mocked_then_runs_with_modified_args() {
+    let values = [1u8, 2, 3];
+    first.mock_safe(|ptr, len| MockResult::Continue((unsafe { ptr.add(1) }, len - 1)));
+
+    assert_eq!(Some(2), first(values.as_ptr(), values.len()));
+    assert_eq!([1, 2, 3], values);
+}
@@ -27,3 +27,24 @@ async fn and_return_mocked_then_returns_mocking_result() {
 
     assert_eq!("mocked true", function(true).await);
 }
+
+#[tokio::test]
+async fn and_return_mocked_with_ready_future_then_returns_mocking_result() {
+    function.mock_safe(|a| MockResult::Return(ready_future(format!("mocked {}", a))));
+
+    assert_eq!("mocked true", function(true).await);
+}
+
+#[mockable]
+async fn function_with_pattern_arg((left, right): (bool, bool)) -> String {
+    format!("{} {}", left, right)
+}
+
+#[tokio::test]
+async fn and_has_pattern_arg_and_continue_mocked_then_runs_with_modified_args() {
+    unsafe {
+        function_with_pattern_arg.mock_raw(|(a, b)| MockResult::Continue(((b, a),)));
+    }
+
+    assert_eq!("false true", function_with_pattern_arg((true, false)).await);
+}
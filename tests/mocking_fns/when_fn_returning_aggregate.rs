@@ -0,0 +1,90 @@
+use super::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Nested = Option<Result<Vec<Box<(String, Rc<()>)>>, String>>;
+
+struct Large {
+    numbers: [u64; 64],
+    text: String,
+    flag: u8,
+}
+
+#[mockable]
+fn collections(arg: u32) -> (Vec<String>, HashMap<u32, u32>) {
+    (vec![arg.to_string()], HashMap::from([(arg, arg)]))
+}
+
+#[mockable]
+fn nested(arg: &Rc<()>) -> Nested {
+    Some(Ok(vec![Box::new(("not mocked".to_string(), arg.clone()))]))
+}
+
+#[mockable]
+fn large(arg: u64) -> Large {
+    Large {
+        numbers: [arg; 64],
+        text: "not mocked".to_string(),
+        flag: 1,
+    }
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    let counter = Rc::new(());
+
+    assert_eq!(
+        (vec!["1".to_string()], HashMap::from([(1, 1)])),
+        collections(1)
+    );
+    let result = nested(&counter);
+    assert_eq!(
+        "not mocked",
+        result.as_ref().unwrap().as_ref().unwrap()[0].0
+    );
+    assert_eq!(2, Rc::strong_count(&counter));
+    drop(result);
+    assert_eq!(1, Rc::strong_count(&counter));
+    let result = large(2);
+    assert_eq!([2; 64], result.numbers);
+    assert_eq!(("not mocked", 1), (result.text.as_str(), result.flag));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    let counter = Rc::new(());
+    collections.mock_safe(|a| {
+        let strings = vec!["mocked".to_string(); a as usize];
+        MockResult::Return((strings, HashMap::from([(a, a * 10), (a + 1, a * 20)])))
+    });
+    let mock_counter = counter.clone();
+    nested.mock_safe(move |_| {
+        let item = Box::new(("mocked".to_string(), mock_counter.clone()));
+        MockResult::Return(Some(Ok(vec![item.clone(), item])))
+    });
+    large.mock_safe(|a| {
+        MockResult::Return(Large {
+            numbers: [a * 2; 64],
+            text: "mocked".to_string(),
+            flag: 2,
+        })
+    });
+
+    assert_eq!(
+        (
+            vec!["mocked".to_string(); 2],
+            HashMap::from([(2, 20), (3, 40)])
+        ),
+        collections(2)
+    );
+    let result = nested(&counter);
+    let items = result.as_ref().unwrap().as_ref().unwrap();
+    assert_eq!(2, items.len());
+    assert_eq!("mocked", items[1].0);
+    assert_eq!(4, Rc::strong_count(&counter));
+    drop(result);
+    assert_eq!(2, Rc::strong_count(&counter));
+    let result = large(2);
+    assert_eq!([4; 64], result.numbers);
+    assert_eq!(("mocked", 2), (result.text.as_str(), result.flag));
+}
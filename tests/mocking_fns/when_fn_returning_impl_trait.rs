@@ -0,0 +1,25 @@
+use super::*;
+
+#[mockable]
+fn function(arg: u32) -> impl Iterator<Item = u32> {
+    vec![arg, arg + 1].into_iter()
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    assert_eq!(vec![1, 2], function(1).collect::<Vec<_>>());
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    function.mock_safe(|a| MockResult::Continue((a + 1,)));
+
+    assert_eq!(vec![2, 3], function(1).collect::<Vec<_>>());
+}
+
+#[test]
+fn and_return_mocked_with_real_result_then_returns_mocking_result() {
+    function.mock_safe(|a| MockResult::Return(function.call_real((a * 10,))));
+
+    assert_eq!(vec![10, 11], function(1).collect::<Vec<_>>());
+}
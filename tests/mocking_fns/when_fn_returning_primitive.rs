@@ -0,0 +1,65 @@
+use super::*;
+
+#[mockable]
+fn number(arg: u32) -> u32 {
+    arg
+}
+
+#[mockable]
+fn tuple(arg: u32) -> (bool, (char, f64)) {
+    (arg > 0, ('a', arg as f64))
+}
+
+#[mockable]
+fn unit(arg: &mut u32) {
+    *arg += 1
+}
+
+#[mockable]
+fn string(arg: u32) -> String {
+    arg.to_string()
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    let mut value = 1;
+    unit(&mut value);
+
+    assert_eq!(1, number(1));
+    assert_eq!((true, ('a', 1.)), tuple(1));
+    assert_eq!(2, value);
+    assert_eq!("1", string(1));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    number.mock_safe(|a| MockResult::Continue((a + 1,)));
+    tuple.mock_safe(|a| MockResult::Continue((a + 1,)));
+    unit.mock_safe(|a| {
+        *a += 10;
+        MockResult::Continue((a,))
+    });
+    string.mock_safe(|a| MockResult::Continue((a + 1,)));
+    let mut value = 1;
+    unit(&mut value);
+
+    assert_eq!(2, number(1));
+    assert_eq!((true, ('a', 2.)), tuple(1));
+    assert_eq!(12, value);
+    assert_eq!("2", string(1));
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    number.mock_safe(|a| MockResult::Return(a + 2));
+    tuple.mock_safe(|_| MockResult::Return((false, ('b', 3.))));
+    unit.mock_safe(|_| MockResult::Return(()));
+    string.mock_safe(|a| MockResult::Return(format!("mocked {}", a)));
+    let mut value = 1;
+    unit(&mut value);
+
+    assert_eq!(3, number(1));
+    assert_eq!((false, ('b', 3.)), tuple(1));
+    assert_eq!(1, value);
+    assert_eq!("mocked 1", string(1));
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+#[mockable]
+fn sum(values: &[i32]) -> i32 {
+    values.iter().sum()
+}
+
+#[mockable]
+fn first(values: &[i32]) -> Option<&i32> {
+    values.first()
+}
+
+#[mockable]
+fn increment(values: &mut [i32]) -> i32 {
+    values.iter_mut().for_each(|value| *value += 1);
+    values.iter().sum()
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    let mut values = [1, 2, 3];
+
+    assert_eq!(6, sum(&values));
+    assert_eq!(Some(&1), first(&values));
+    assert_eq!(9, increment(&mut values));
+    assert_eq!([2, 3, 4], values);
+}
+
+#[test]
+fn and_return_mocked_then_mock_inspects_slice() {
+    sum.mock_safe(|values| MockResult::Return(values.iter().map(|value| value * 10).sum()));
+
+    assert_eq!(60, sum(&[1, 2, 3]));
+    assert_eq!(0, sum(&[]));
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_subslice() {
+    sum.mock_safe(|values| MockResult::Continue((&values[1..],)));
+
+    assert_eq!(5, sum(&[1, 2, 3]));
+}
+
+#[test]
+fn and_return_mocked_then_returns_reference_borrowed_from_slice() {
+    first.mock_safe(|values| MockResult::Return(values.last()));
+
+    assert_eq!(Some(&3), first(&[1, 2, 3]));
+}
+
+#[test]
+fn and_mutable_slice_return_mocked_then_mock_modifies_slice() {
+    increment.mock_safe(|values| {
+        values.iter_mut().for_each(|value| *value *= 2);
+        MockResult::Return(0)
+    });
+    let mut values = [1, 2, 3];
+
+    assert_eq!(0, increment(&mut values));
+    assert_eq!([2, 4, 6], values);
+}
+
+#[test]
+fn and_mutable_slice_continue_mocked_then_runs_with_slice_modified_by_mock() {
+    increment.mock_safe(|values| {
+        values[0] = 10;
+        MockResult::Continue((values,))
+    });
+    let mut values = [1, 2, 3];
+
+    assert_eq!(18, increment(&mut values));
+    assert_eq!([11, 3, 4], values);
+}
+
+#[test]
+fn and_mutable_slice_continue_mocked_then_runs_with_subslice() {
+    increment.mock_safe(|values| MockResult::Continue((&mut values[1..],)));
+    let mut values = [1, 2, 3];
+
+    assert_eq!(7, increment(&mut values));
+    assert_eq!([1, 3, 4], values);
+}
@@ -0,0 +1,51 @@
+use super::*;
+use std::panic::Location;
+
+#[mockable]
+#[track_caller]
+fn function(arg: u32) -> (u32, u32) {
+    (arg, Location::caller().line())
+}
+
+struct Struct;
+
+#[mockable]
+impl Struct {
+    #[track_caller]
+    fn method(&self) -> u32 {
+        Location::caller().line()
+    }
+}
+
+#[test]
+fn and_not_mocked_then_reports_caller_location() {
+    let line = line!() + 1;
+    let result = function(1);
+
+    assert_eq!((1, line), result);
+}
+
+#[test]
+fn and_continue_mocked_then_reports_caller_location() {
+    function.mock_safe(|a| MockResult::Continue((a + 1,)));
+
+    let line = line!() + 1;
+    let result = function(1);
+
+    assert_eq!((2, line), result);
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    function.mock_safe(|a| MockResult::Return((a, 0)));
+
+    assert_eq!((1, 0), function(1));
+}
+
+#[test]
+fn and_method_not_mocked_then_reports_caller_location() {
+    let line = line!() + 1;
+    let result = Struct.method();
+
+    assert_eq!(line, result);
+}
@@ -0,0 +1,128 @@
+use super::*;
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+thread_local! {
+    static DROPPED_TOKENS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, PartialEq)]
+struct Marker;
+
+mod other {
+    /// Type sharing its name with the marker, but not zero sized
+    #[derive(Debug, PartialEq)]
+    pub struct PhantomData(pub u8);
+}
+
+/// Zero sized type with drop glue, which must be dropped exactly once
+struct Token;
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        DROPPED_TOKENS.with(|dropped| dropped.set(dropped.get() + 1));
+    }
+}
+
+fn dropped_tokens() -> usize {
+    DROPPED_TOKENS.with(Cell::get)
+}
+
+#[mockable]
+fn phantom(_: PhantomData<u8>) {}
+
+#[mockable]
+fn phantom_ref<'a>(marker: PhantomData<&'a str>, text: &'a str) -> (PhantomData<&'a str>, &'a str) {
+    (marker, text)
+}
+
+#[mockable]
+fn phantom_returned(_: &str) -> PhantomData<&str> {
+    PhantomData
+}
+
+#[mockable]
+fn phantom_full_path(_: core::marker::PhantomData<u8>) -> std::marker::PhantomData<u8> {
+    PhantomData
+}
+
+#[mockable]
+fn phantom_same_name(arg: other::PhantomData) -> other::PhantomData {
+    arg
+}
+
+#[mockable]
+fn marker(arg: Marker, flag: bool) -> (Marker, bool) {
+    (arg, flag)
+}
+
+#[mockable]
+fn consume(token: Token) -> Token {
+    token
+}
+
+#[test]
+fn and_not_mocked_then_runs_normally() {
+    phantom(PhantomData);
+    assert_eq!((PhantomData, "text"), phantom_ref(PhantomData, "text"));
+    assert_eq!(PhantomData, phantom_returned("text"));
+    assert_eq!(PhantomData, phantom_full_path(PhantomData));
+    assert_eq!(
+        other::PhantomData(1),
+        phantom_same_name(other::PhantomData(1))
+    );
+    assert_eq!((Marker, true), marker(Marker, true));
+    drop(consume(Token));
+
+    assert_eq!(1, phantom.call_count());
+    assert_eq!(1, dropped_tokens());
+}
+
+#[test]
+fn and_return_mocked_then_returns_mocking_result() {
+    phantom.mock_safe(|_| MockResult::Return(()));
+    phantom_ref.mock_safe(|marker, _| MockResult::Return((marker, "mocked")));
+    phantom_returned.mock_safe(|_| MockResult::Return(PhantomData));
+    phantom_full_path.mock_safe(|_| MockResult::Return(PhantomData));
+    phantom_same_name.mock_safe(|_| MockResult::Return(other::PhantomData(2)));
+    marker.mock_safe(|_, flag| MockResult::Return((Marker, !flag)));
+    consume.mock_safe(MockResult::Return);
+
+    phantom(PhantomData);
+    assert_eq!((PhantomData, "mocked"), phantom_ref(PhantomData, "text"));
+    assert_eq!(PhantomData, phantom_returned("text"));
+    assert_eq!(PhantomData, phantom_full_path(PhantomData));
+    assert_eq!(
+        other::PhantomData(2),
+        phantom_same_name(other::PhantomData(1))
+    );
+    assert_eq!((Marker, false), marker(Marker, true));
+    drop(consume(Token));
+
+    assert_eq!(1, phantom.call_count());
+    assert_eq!(1, dropped_tokens());
+}
+
+#[test]
+fn and_continue_mocked_then_runs_with_modified_args() {
+    phantom.mock_safe(|arg| MockResult::Continue((arg,)));
+    phantom_ref.mock_safe(|marker, _| MockResult::Continue((marker, "modified")));
+    phantom_same_name.mock_safe(|arg| MockResult::Continue((other::PhantomData(arg.0 + 1),)));
+    marker.mock_safe(|arg, flag| MockResult::Continue((arg, !flag)));
+    consume.mock_safe(|token| {
+        drop(token);
+        MockResult::Continue((Token,))
+    });
+
+    phantom(PhantomData);
+    assert_eq!((PhantomData, "modified"), phantom_ref(PhantomData, "text"));
+    assert_eq!(
+        other::PhantomData(2),
+        phantom_same_name(other::PhantomData(1))
+    );
+    assert_eq!((Marker, false), marker(Marker, true));
+    drop(consume(Token));
+
+    assert_eq!(1, phantom.call_count());
+    assert_eq!(2, dropped_tokens());
+}
@@ -1,6 +1,8 @@
 use super::*;
 
+mod when_struct_dropping_method_regular;
 mod when_struct_generic_method_generic;
 mod when_struct_generic_method_regular;
 mod when_struct_regular_method_generic;
 mod when_struct_regular_method_regular;
+mod when_struct_wrapped_self_method_regular;
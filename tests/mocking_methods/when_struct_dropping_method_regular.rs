@@ -0,0 +1,201 @@
+use super::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// Counts drops to detect receivers leaked or dropped twice by injected header
+struct Struct {
+    value: u8,
+    drops: Rc<Cell<u32>>,
+}
+
+impl Struct {
+    fn new(value: u8, drops: &Rc<Cell<u32>>) -> Self {
+        Struct {
+            value,
+            drops: drops.clone(),
+        }
+    }
+}
+
+impl Drop for Struct {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+#[mockable]
+impl Struct {
+    fn ref_method(&self) -> u8 {
+        self.value
+    }
+
+    fn ref_mut_method(&mut self) -> u8 {
+        self.value *= 2;
+        self.value
+    }
+
+    fn val_method(self) -> u8 {
+        self.value
+    }
+
+    fn box_method(self: Box<Self>) -> u8 {
+        self.value
+    }
+}
+
+mod and_method_is_ref_method {
+    use super::*;
+
+    #[test]
+    fn and_continue_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::ref_method.mock_safe(|s| MockResult::Continue((s,)));
+
+        let result = Struct::new(2, &drops).ref_method();
+
+        assert_eq!(2, result);
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn and_return_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::ref_method.mock_safe(|_| MockResult::Return(3));
+
+        let result = Struct::new(2, &drops).ref_method();
+
+        assert_eq!(3, result);
+        assert_eq!(1, drops.get());
+    }
+}
+
+mod and_method_is_ref_mut_method {
+    use super::*;
+
+    #[test]
+    fn and_continue_mocked_then_modifies_receiver_and_drops_it_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::ref_mut_method.mock_safe(|s| MockResult::Continue((s,)));
+        let mut receiver = Struct::new(2, &drops);
+
+        let result = receiver.ref_mut_method();
+
+        assert_eq!(4, result);
+        assert_eq!(4, receiver.value);
+        drop(receiver);
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn and_return_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::ref_mut_method.mock_safe(|_| MockResult::Return(3));
+        let mut receiver = Struct::new(2, &drops);
+
+        let result = receiver.ref_mut_method();
+
+        assert_eq!(3, result);
+        assert_eq!(2, receiver.value);
+        drop(receiver);
+        assert_eq!(1, drops.get());
+    }
+}
+
+mod and_method_is_val_method {
+    use super::*;
+
+    #[test]
+    fn and_not_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+
+        let result = Struct::new(2, &drops).val_method();
+
+        assert_eq!(2, result);
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn and_continue_mocked_with_same_receiver_then_drops_it_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::val_method.mock_safe(|s| MockResult::Continue((s,)));
+
+        let result = Struct::new(2, &drops).val_method();
+
+        assert_eq!(2, result);
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn and_continue_mocked_with_replaced_receiver_then_drops_both_once() {
+        let drops = Rc::new(Cell::new(0));
+        let mock_drops = drops.clone();
+        Struct::val_method.mock_safe(move |_| MockResult::Continue((Struct::new(3, &mock_drops),)));
+
+        let result = Struct::new(2, &drops).val_method();
+
+        assert_eq!(3, result);
+        assert_eq!(2, drops.get());
+    }
+
+    #[test]
+    fn and_return_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::val_method.mock_safe(|_| MockResult::Return(3));
+
+        let result = Struct::new(2, &drops).val_method();
+
+        assert_eq!(3, result);
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn and_mock_panics_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::val_method.mock_safe(|_| panic!("mock panic"));
+        let receiver = Struct::new(2, &drops);
+
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| receiver.val_method()));
+
+        assert!(result.is_err());
+        assert_eq!(1, drops.get());
+    }
+}
+
+mod and_method_is_box_method {
+    use super::*;
+
+    #[test]
+    fn and_not_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+
+        let result = Box::new(Struct::new(2, &drops)).box_method();
+
+        assert_eq!(2, result);
+        assert_eq!(1, drops.get());
+    }
+
+    #[test]
+    fn and_continue_mocked_with_replaced_receiver_then_drops_both_once() {
+        let drops = Rc::new(Cell::new(0));
+        let mock_drops = drops.clone();
+        Struct::box_method
+            .mock_safe(move |_| MockResult::Continue((Box::new(Struct::new(3, &mock_drops)),)));
+
+        let result = Box::new(Struct::new(2, &drops)).box_method();
+
+        assert_eq!(3, result);
+        assert_eq!(2, drops.get());
+    }
+
+    #[test]
+    fn and_return_mocked_then_drops_receiver_once() {
+        let drops = Rc::new(Cell::new(0));
+        Struct::box_method.mock_safe(|_| MockResult::Return(3));
+
+        let result = Box::new(Struct::new(2, &drops)).box_method();
+
+        assert_eq!(3, result);
+        assert_eq!(1, drops.get());
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+struct Struct(u8);
+
+#[mockable]
+impl Struct {
+    fn rc_method(self: Rc<Self>) -> u8 {
+        self.0
+    }
+
+    fn arc_method(self: Arc<Self>) -> u8 {
+        self.0
+    }
+
+    fn pin_ref_mut_method(mut self: Pin<&mut Self>) -> u8 {
+        self.0 *= 2;
+        self.0
+    }
+
+    fn pin_box_method(self: Pin<Box<Self>>) -> u8 {
+        self.0
+    }
+}
+
+mod and_method_is_rc_method {
+    use super::*;
+
+    #[test]
+    fn and_not_mocked_then_runs_normally_and_releases_receiver() {
+        let receiver = Rc::new(Struct(2));
+
+        assert_eq!(2, receiver.clone().rc_method());
+        assert_eq!(1, Rc::strong_count(&receiver));
+    }
+
+    #[test]
+    fn and_continue_mocked_then_runs_with_modified_args_and_releases_receivers() {
+        let receiver = Rc::new(Struct(2));
+        let replacement = Rc::new(Struct(3));
+        let mock_replacement = replacement.clone();
+        Struct::rc_method.mock_safe(move |_| MockResult::Continue((mock_replacement.clone(),)));
+
+        assert_eq!(3, receiver.clone().rc_method());
+        assert_eq!(1, Rc::strong_count(&receiver));
+        assert_eq!(2, Rc::strong_count(&replacement));
+    }
+
+    #[test]
+    fn and_return_mocked_then_returns_mocking_result_and_releases_receiver() {
+        let receiver = Rc::new(Struct(2));
+        Struct::rc_method.mock_safe(|s| MockResult::Return(s.0 + 1));
+
+        assert_eq!(3, receiver.clone().rc_method());
+        assert_eq!(1, Rc::strong_count(&receiver));
+    }
+}
+
+mod and_method_is_arc_method {
+    use super::*;
+
+    #[test]
+    fn and_not_mocked_then_runs_normally_and_releases_receiver() {
+        let receiver = Arc::new(Struct(2));
+
+        assert_eq!(2, receiver.clone().arc_method());
+        assert_eq!(1, Arc::strong_count(&receiver));
+    }
+
+    #[test]
+    fn and_return_mocked_then_returns_mocking_result_and_releases_receiver() {
+        let receiver = Arc::new(Struct(2));
+        Struct::arc_method.mock_safe(|s| MockResult::Return(s.0 + 1));
+
+        assert_eq!(3, receiver.clone().arc_method());
+        assert_eq!(1, Arc::strong_count(&receiver));
+    }
+}
+
+mod and_method_is_pin_ref_mut_method {
+    use super::*;
+
+    #[test]
+    fn and_not_mocked_then_runs_normally() {
+        let mut receiver = Struct(2);
+
+        assert_eq!(4, Pin::new(&mut receiver).pin_ref_mut_method());
+        assert_eq!(4, receiver.0);
+    }
+
+    #[test]
+    fn and_continue_mocked_then_runs_with_modified_args() {
+        Struct::pin_ref_mut_method.mock_safe(|mut s| {
+            s.0 += 1;
+            MockResult::Continue((s,))
+        });
+        let mut receiver = Struct(2);
+
+        assert_eq!(6, Pin::new(&mut receiver).pin_ref_mut_method());
+        assert_eq!(6, receiver.0);
+    }
+
+    #[test]
+    fn and_return_mocked_then_returns_mocking_result() {
+        Struct::pin_ref_mut_method.mock_safe(|s| MockResult::Return(s.0 + 1));
+        let mut receiver = Struct(2);
+
+        assert_eq!(3, Pin::new(&mut receiver).pin_ref_mut_method());
+        assert_eq!(2, receiver.0);
+    }
+}
+
+mod and_method_is_pin_box_method {
+    use super::*;
+
+    #[test]
+    fn and_not_mocked_then_runs_normally() {
+        assert_eq!(2, Box::pin(Struct(2)).pin_box_method());
+    }
+
+    #[test]
+    fn and_continue_mocked_then_runs_with_modified_args() {
+        Struct::pin_box_method.mock_safe(|_| MockResult::Continue((Box::pin(Struct(3)),)));
+
+        assert_eq!(3, Box::pin(Struct(2)).pin_box_method());
+    }
+
+    #[test]
+    fn and_return_mocked_then_returns_mocking_result() {
+        Struct::pin_box_method.mock_safe(|s| MockResult::Return(s.0 + 1));
+
+        assert_eq!(3, Box::pin(Struct(2)).pin_box_method());
+    }
+}
@@ -5,3 +5,4 @@ mod when_struct_generic_method_generic_async;
 mod when_struct_generic_method_regular_async;
 mod when_struct_regular_method_generic_async;
 mod when_struct_regular_method_regular_async;
+mod when_trait_method_async_trait_expanded;
@@ -0,0 +1,60 @@
+use super::*;
+use std::future::Future;
+use std::pin::Pin;
+
+// Trait and impl in the shape generated by `#[async_trait]`
+trait Trait {
+    fn method<'life0, 'async_trait>(
+        &'life0 self,
+        arg: u32,
+    ) -> Pin<Box<dyn Future<Output = String> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        Self: 'async_trait;
+}
+
+struct Struct(u32);
+
+#[mockable]
+impl Trait for Struct {
+    fn method<'life0, 'async_trait>(
+        &'life0 self,
+        arg: u32,
+    ) -> Pin<Box<dyn Future<Output = String> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async move { format!("{} {}", self.0, arg) })
+    }
+}
+
+#[tokio::test]
+async fn and_not_mocked_then_runs_normally() {
+    assert_eq!("1 2", Struct(1).method(2).await);
+}
+
+#[tokio::test]
+async fn and_continue_mocked_then_runs_with_modified_args() {
+    <Struct as Trait>::method.mock_safe(|s, a| MockResult::Continue((s, a + 1)));
+
+    assert_eq!("1 3", Struct(1).method(2).await);
+}
+
+#[tokio::test]
+async fn and_return_mocked_then_returns_mocking_result() {
+    <Struct as Trait>::method
+        .mock_safe(|_, a| MockResult::Return(ready_future(format!("mocked {}", a))));
+
+    assert_eq!("mocked 2", Struct(1).method(2).await);
+}
+
+#[tokio::test]
+async fn and_return_mocked_with_async_block_then_returns_its_result() {
+    <Struct as Trait>::method.mock_safe(|s, a| {
+        let value = s.0;
+        MockResult::Return(Box::pin(async move { format!("mocked {} {}", value, a) }))
+    });
+
+    assert_eq!("mocked 1 2", Struct(1).method(2).await);
+}
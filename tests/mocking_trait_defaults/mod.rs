@@ -37,3 +37,58 @@ mod mocking_default_impl_of_trait_of_struct {
         assert_eq!("not mocked", Struct2::method());
     }
 }
+
+mod mocking_methods_called_by_default_method {
+    use super::*;
+
+    #[mockable]
+    trait Trait {
+        fn required(&self) -> u32;
+
+        fn helper(&self) -> u32 {
+            10
+        }
+
+        fn method(&self) -> u32 {
+            self.required() + self.helper()
+        }
+    }
+
+    struct Struct;
+
+    #[mockable]
+    impl Trait for Struct {
+        fn required(&self) -> u32 {
+            1
+        }
+    }
+
+    #[test]
+    fn and_not_mocked_then_runs_normally() {
+        assert_eq!(11, Struct.method());
+    }
+
+    #[test]
+    fn and_default_helper_mocked_then_default_method_uses_mock() {
+        Struct::helper.mock_safe(|_| MockResult::Return(20));
+
+        assert_eq!(21, Struct.method());
+    }
+
+    #[test]
+    fn and_implemented_helper_mocked_then_default_method_uses_mock() {
+        Struct::required.mock_safe(|_| MockResult::Return(2));
+
+        assert_eq!(12, Struct.method());
+    }
+
+    #[test]
+    fn and_default_method_continue_mocked_then_it_uses_helper_mocks() {
+        Struct::method.mock_safe(|s| MockResult::Continue((s,)));
+        Struct::helper.mock_safe(|_| MockResult::Return(20));
+        Struct::required.mock_safe(|_| MockResult::Return(2));
+
+        assert_eq!(22, Struct.method());
+        assert_eq!(1, Struct::method.call_count());
+    }
+}
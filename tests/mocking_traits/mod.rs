@@ -63,3 +63,189 @@ mod mocking_impls_of_traits_generic_over_generic_refs {
         assert_eq!("mocked", <Struct as Trait<&u32>>::method());
     }
 }
+
+mod mocking_trait_methods_generic_over_arg_type_per_instantiation {
+    use super::*;
+
+    struct Struct(u32);
+
+    trait Converter {
+        fn convert<U: Display>(&self, u: U) -> String;
+    }
+
+    #[mockable]
+    impl Converter for Struct {
+        fn convert<U: Display>(&self, u: U) -> String {
+            format!("{} {}", self.0, u)
+        }
+    }
+
+    #[test]
+    fn mocks_each_instantiation_distinctly() {
+        Struct::convert::<i32>.mock_safe(|_, u| MockResult::Return(format!("mocked i32 {}", u)));
+        Struct::convert::<&str>
+            .mock_safe(|s, u| MockResult::Return(format!("mocked str {} {}", s.0, u)));
+
+        assert_eq!("mocked i32 2", Struct(1).convert(2i32));
+        assert_eq!("mocked str 1 abc", Struct(1).convert("abc"));
+        assert_eq!("1 2", Struct(1).convert(2u8));
+    }
+
+    #[test]
+    fn continues_each_instantiation_distinctly() {
+        Struct::convert::<i32>.mock_safe(|s, u| MockResult::Continue((s, u * 10)));
+
+        assert_eq!("1 20", Struct(1).convert(2i32));
+        assert_eq!("1 abc", Struct(1).convert("abc"));
+        assert_eq!(1, Struct::convert::<i32>.call_count());
+        assert_eq!(1, Struct::convert::<&str>.call_count());
+    }
+}
+
+mod mocking_impls_of_std_traits_with_associated_types {
+    use super::*;
+    use std::ops::Add;
+
+    struct Counter {
+        count: u32,
+    }
+
+    #[mockable]
+    impl Iterator for Counter {
+        type Item = u32;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.count < 3 {
+                self.count += 1;
+                Some(self.count)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Number(u32);
+
+    #[mockable]
+    impl Add for Number {
+        type Output = Self;
+
+        fn add(self, rhs: Self) -> Self::Output {
+            Number(self.0 + rhs.0)
+        }
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(vec![1, 2, 3], Counter { count: 0 }.collect::<Vec<_>>());
+        assert_eq!(Number(3), Number(1) + Number(2));
+    }
+
+    #[test]
+    fn when_iterator_mocked_then_adapters_use_mock() {
+        <Counter as Iterator>::next.mock_returns_seq(vec![Some(10), Some(20), None]);
+
+        assert_eq!(vec![10, 20], Counter { count: 0 }.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn when_iterator_continue_mocked_then_runs_with_modified_receiver() {
+        <Counter as Iterator>::next.mock_safe(|counter| {
+            counter.count += 1;
+            MockResult::Continue((counter,))
+        });
+
+        assert_eq!(vec![2], Counter { count: 0 }.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn when_operator_mocked_then_returns_mocking_result() {
+        <Number as Add>::add.mock_safe(|a, b| MockResult::Return(Number(a.0 * b.0)));
+
+        assert_eq!(Number(6), Number(2) + Number(3));
+    }
+}
+
+mod mocking_impls_of_traits_with_generic_and_absolute_paths {
+    use super::*;
+    use std::ops::Add;
+    use traits::Named;
+
+    // Shadows the `core` crate, so it's reachable only through an absolute path
+    mod core {}
+
+    mod traits {
+        pub trait Named {
+            fn name() -> &'static str;
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Number(u32);
+
+    #[mockable]
+    impl From<u32> for Number {
+        fn from(value: u32) -> Self {
+            Number(value)
+        }
+    }
+
+    #[mockable]
+    impl ::core::convert::From<&str> for Number {
+        fn from(value: &str) -> Self {
+            Number(value.len() as u32)
+        }
+    }
+
+    #[mockable]
+    impl ::core::ops::Add<u32> for Number {
+        type Output = Number;
+
+        fn add(self, rhs: u32) -> Number {
+            Number(self.0 + rhs)
+        }
+    }
+
+    #[mockable]
+    impl crate::mocking_traits::mocking_impls_of_traits_with_generic_and_absolute_paths::traits::Named
+        for Number
+    {
+        fn name() -> &'static str {
+            "not mocked"
+        }
+    }
+
+    #[test]
+    fn when_not_mocked_then_runs_normally() {
+        assert_eq!(Number(1), Number::from(1));
+        assert_eq!(Number(3), Number::from("abc"));
+        assert_eq!(Number(3), Number(1) + 2);
+        assert_eq!("not mocked", Number::name());
+    }
+
+    #[test]
+    fn when_generic_trait_mocked_then_returns_mocking_result() {
+        <Number as From<u32>>::from.mock_safe(|value| MockResult::Return(Number(value * 10)));
+
+        assert_eq!(Number(10), Number::from(1));
+        assert_eq!(Number(3), Number::from("abc"));
+    }
+
+    #[test]
+    fn when_absolute_generic_trait_mocked_then_returns_mocking_result() {
+        <Number as From<&str>>::from.mock_safe(|_| MockResult::Return(Number(0)));
+        <Number as Add<u32>>::add.mock_safe(|a, b| MockResult::Continue((a, b * 10)));
+
+        assert_eq!(Number(0), Number::from("abc"));
+        assert_eq!(Number(21), Number(1) + 2);
+        assert_eq!(Number(1), Number::from(1));
+    }
+
+    #[test]
+    fn when_crate_path_trait_mocked_then_returns_mocking_result() {
+        Number::name.mock_safe(|| MockResult::Return("mocked"));
+
+        assert_eq!("mocked", Number::name());
+    }
+}
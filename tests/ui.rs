@@ -0,0 +1,90 @@
+//! Builds fixtures from `tests/ui` as separate crates depending on mocktopus and checks results
+#![cfg(feature = "enabled")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+fn fixture_path(file_name: &str) -> PathBuf {
+    Path::new(MANIFEST_DIR)
+        .join("tests")
+        .join("ui")
+        .join(file_name)
+}
+
+/// Runs cargo `command` on binary crate with `main.rs` copied from fixture `name`,
+/// which depends on mocktopus with `features` enabled
+fn cargo_fixture(command: &str, name: &str, features: &[&str]) -> Output {
+    let ui_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ui");
+    let crate_dir = ui_dir.join(name);
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    let manifest = format!(
+        "[package]\n\
+         name = \"{name}\"\n\
+         version = \"0.0.0\"\n\
+         edition = \"2018\"\n\
+         \n\
+         [dependencies]\n\
+         mocktopus = {{ path = '{mocktopus}', default-features = false, features = {features:?} }}\n\
+         \n\
+         [workspace]\n",
+        name = name,
+        mocktopus = MANIFEST_DIR,
+        features = features,
+    );
+    fs::write(crate_dir.join("Cargo.toml"), manifest).unwrap();
+    fs::copy(
+        fixture_path(&format!("{}.rs", name)),
+        crate_dir.join("src").join("main.rs"),
+    )
+    .unwrap();
+    // Use the same dependencies, which were already fetched for building this crate
+    let _ = fs::copy(
+        Path::new(MANIFEST_DIR).join("Cargo.lock"),
+        crate_dir.join("Cargo.lock"),
+    );
+    Command::new(env!("CARGO"))
+        .arg(command)
+        .args(["--quiet", "--offline", "--color", "never", "--target-dir"])
+        .arg(ui_dir.join("target"))
+        .current_dir(&crate_dir)
+        .output()
+        .unwrap()
+}
+
+/// Diagnostics of the compiler without the final summary printed by cargo
+fn compiler_stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| !line.starts_with("error: could not compile"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn expected_stderr(name: &str) -> String {
+    fs::read_to_string(fixture_path(&format!("{}.stderr", name))).unwrap()
+}
+
+#[test]
+fn mockable_on_unsupported_items_fails_to_compile() {
+    let output = cargo_fixture("build", "mockable_unsupported_items", &["enabled"]);
+
+    assert!(!output.status.success());
+    assert_eq!(
+        expected_stderr("mockable_unsupported_items").trim(),
+        compiler_stderr(&output).trim()
+    );
+}
+
+#[test]
+fn test_only_fns_outside_of_test_build_are_not_mockable() {
+    let output = cargo_fixture(
+        "run",
+        "test_only_outside_of_test_build",
+        &["enabled", "test-only"],
+    );
+
+    assert!(output.status.success(), "{}", compiler_stderr(&output));
+}
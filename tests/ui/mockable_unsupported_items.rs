@@ -0,0 +1,12 @@
+use mocktopus::macros::mockable;
+
+#[mockable]
+struct Struct;
+
+#[mockable]
+static STATIC: u32 = 0;
+
+#[mockable]
+type Alias = Struct;
+
+fn main() {}
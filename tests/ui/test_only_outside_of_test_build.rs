@@ -0,0 +1,27 @@
+use mocktopus::macros::mockable;
+use mocktopus::mocking::{MockResult, Mockable};
+
+#[mockable]
+fn function(arg: u32) -> u32 {
+    arg * 2
+}
+
+struct Struct;
+
+#[mockable]
+impl Struct {
+    fn method(&self) -> &'static str {
+        "not mocked"
+    }
+}
+
+// Without mock headers the functions never look up their mocks, so the mocks have no effect
+fn main() {
+    function.mock_safe(|_| MockResult::Return(0));
+    Struct::method.mock_safe(|_| MockResult::Return("mocked"));
+
+    assert_eq!(4, function(2));
+    assert_eq!("not mocked", Struct.method());
+    assert_eq!(0, function.call_count());
+    assert_eq!(0, Struct::method.call_count());
+}